@@ -1,11 +1,17 @@
+use crate::builds::commits::CommitInfo;
+use crate::github::aggregate::AggregateBuild;
+use crate::history::store::BuildHistory;
+use crate::i18n::Locale;
+use crate::mirror::storage::MirrorStorage;
+use crate::queue::worker::EventQueue;
 use moka::future::Cache;
-use octocrab::models::RunId;
-use octocrab::models::webhook_events::payload::PushWebhookEventCommit;
-use serenity::all::{EmojiId, MessageId};
+use octocrab::models::{ReleaseId, RepositoryId};
+use serenity::all::{ChannelId, EmojiId, GuildId, MessageId};
 use serenity::http::Http;
 use std::env;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 #[derive(Clone)]
 pub struct App {
@@ -13,12 +19,34 @@ pub struct App {
     pub serenity_http: Arc<Http>,
     pub cache: AppCache,
     pub emojis: Emojis,
+    pub history: BuildHistory,
+    pub queue: EventQueue,
+    pub mirror: MirrorStorage,
+    pub github_token: Option<String>,
+    pub gitlab_token: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct AppCache {
-    pub commits: Cache<String, Vec<PushWebhookEventCommit>>,
-    pub running_workflows: Cache<RunId, MessageId>,
+    pub commits: Cache<String, Vec<CommitInfo>>,
+    pub running_workflows: Cache<(Forge, u64), MessageId>,
+    /// Head SHA of the last build announced per repository and branch
+    pub announced_builds: Cache<(RepositoryId, String), String>,
+    pub announced_releases: Cache<ReleaseId, MessageId>,
+    pub channel_guilds: Cache<ChannelId, Option<GuildId>>,
+    /// Discussion thread opened on a run's message, with the language its job results are posted in
+    pub build_threads: Cache<(Forge, u64), (ChannelId, Locale)>,
+    pub run_locks: Cache<(Forge, u64), Arc<Mutex<()>>>,
+    pub release_locks: Cache<ReleaseId, Arc<Mutex<()>>>,
+    /// Shared message state per repository and head SHA for aggregated workflows
+    pub aggregates: Cache<(String, String), Arc<Mutex<AggregateBuild>>>,
+}
+
+/// Where a run comes from, run ids are only unique within one forge so caches keep them apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Forge {
+    Github,
+    Gitlab,
 }
 
 #[derive(Clone)]
@@ -29,11 +57,11 @@ pub struct Emojis {
 }
 
 impl App {
-    pub fn new(serenity_http: Arc<Http>) -> Self {
+    pub fn new(serenity_http: Arc<Http>, history: BuildHistory) -> Self {
         let ttl_time = env::var("CACHE_TTL")
             .ok()
             .and_then(|ttl| ttl.parse::<u64>().ok())
-            .unwrap_or_else(|| 60);
+            .unwrap_or(60);
         let cache_ttl = Duration::from_secs(ttl_time * 60);
 
         Self {
@@ -42,14 +70,90 @@ impl App {
             cache: AppCache {
                 commits: Cache::builder().time_to_live(cache_ttl).build(),
                 running_workflows: Cache::builder().time_to_live(cache_ttl).build(),
+                announced_builds: Cache::builder().max_capacity(10_000).build(),
+                announced_releases: Cache::builder().time_to_live(cache_ttl).build(),
+                channel_guilds: Cache::builder().max_capacity(1_000).build(),
+                build_threads: Cache::builder().time_to_live(cache_ttl).build(),
+                run_locks: Cache::builder().time_to_idle(cache_ttl).build(),
+                release_locks: Cache::builder().time_to_idle(cache_ttl).build(),
+                aggregates: Cache::builder().time_to_idle(cache_ttl).build(),
             },
             emojis: Emojis {
                 processing: Emojis::for_env_var("PROCESSING_EMOJI"),
                 success: Emojis::for_env_var("SUCCESS_EMOJI"),
                 failed: Emojis::for_env_var("FAILED_EMOJI"),
             },
+            history,
+            queue: EventQueue::new(),
+            mirror: MirrorStorage::from_env(),
+            github_token: env::var("GITHUB_TOKEN").ok(),
+            gitlab_token: env::var("GITLAB_TOKEN").ok(),
         }
     }
+
+    /// Waits until no other event of the run is being handled
+    pub async fn lock_run(&self, run: (Forge, u64)) -> OwnedMutexGuard<()> {
+        self.cache
+            .run_locks
+            .get_with(run, async { Arc::new(Mutex::new(())) })
+            .await
+            .lock_owned()
+            .await
+    }
+
+    /// Waits until no other event of the release is being handled
+    pub async fn lock_release(&self, release_id: ReleaseId) -> OwnedMutexGuard<()> {
+        self.cache
+            .release_locks
+            .get_with(release_id, async { Arc::new(Mutex::new(())) })
+            .await
+            .lock_owned()
+            .await
+    }
+
+    /// Starts a request against the GitHub API, authenticated when a token is configured
+    pub fn github_get(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        let request = self.https.get(url).header(
+            "User-Agent",
+            "CI-Preview (https://github.com/IThundxr/CI-Preview)",
+        );
+
+        match &self.github_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Starts a request against a GitLab instance's API, authenticated when a token is configured
+    pub fn gitlab_get(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        let request = self.https.get(url).header(
+            "User-Agent",
+            "CI-Preview (https://github.com/IThundxr/CI-Preview)",
+        );
+
+        match &self.gitlab_token {
+            Some(token) => request.header("PRIVATE-TOKEN", token),
+            None => request,
+        }
+    }
+
+    /// Link to a message, looking up which guild the channel belongs to
+    pub async fn message_link(&self, channel_id: ChannelId, message_id: MessageId) -> String {
+        let guild_id = self
+            .cache
+            .channel_guilds
+            .get_with(channel_id, async {
+                channel_id
+                    .to_channel(&self.serenity_http)
+                    .await
+                    .ok()
+                    .and_then(|channel| channel.guild())
+                    .map(|channel| channel.guild_id)
+            })
+            .await;
+
+        message_id.link(channel_id, guild_id)
+    }
 }
 
 impl Emojis {
@@ -0,0 +1,177 @@
+use crate::app::App;
+use crate::config::repo_config::MentionSettings;
+use crate::history::store::Build;
+use crate::i18n::Locale;
+use crate::util::time::format_duration;
+use serenity::all::{ChannelId, CreateAllowedMentions, CreateMessage, Mention, MessageId};
+use tracing::log::error;
+
+enum Transition {
+    Broken,
+    Fixed,
+}
+
+/// Pings the configured roles and users when a branch goes from passing to failing, and follows
+/// up once it passes again
+pub async fn notify_transition(
+    app: &App,
+    locale: Locale,
+    mentions: &[MentionSettings],
+    channel_id: ChannelId,
+    message_id: MessageId,
+    build: &Build,
+    previous: Option<&Build>,
+) {
+    let Some(previous) = previous else {
+        return;
+    };
+
+    let transition = if is_success(previous) && is_failed(build) {
+        Transition::Broken
+    } else if is_failed(previous) && is_success(build) {
+        Transition::Fixed
+    } else {
+        return;
+    };
+
+    let rules = mentions
+        .iter()
+        .filter(|rule| rule.applies_to(&build.branch))
+        .filter(|rule| matches!(transition, Transition::Broken) || rule.fixed)
+        .collect::<Vec<&MentionSettings>>();
+
+    if rules.is_empty() {
+        return;
+    }
+
+    let build_link = format!("[#{}](<{}>)", build.run_number, build.url);
+
+    let message = match transition {
+        Transition::Broken => {
+            let roles = rules
+                .iter()
+                .flat_map(|rule| rule.roles.clone())
+                .collect::<Vec<_>>();
+            let users = rules
+                .iter()
+                .flat_map(|rule| rule.users.clone())
+                .collect::<Vec<_>>();
+
+            let pings = roles
+                .iter()
+                .map(|role| Mention::Role(*role).to_string())
+                .chain(users.iter().map(|user| Mention::User(*user).to_string()))
+                .collect::<Vec<String>>()
+                .join(" ");
+
+            CreateMessage::new()
+                .content(format!(
+                    "{pings}\n{}",
+                    locale.format(
+                        "alert.broken",
+                        &[("branch", &build.branch), ("build", &build_link)]
+                    )
+                ))
+                // Only ever ping who's configured, whatever ends up in the message
+                .allowed_mentions(CreateAllowedMentions::new().roles(roles).users(users))
+        }
+        Transition::Fixed => CreateMessage::new()
+            .content(locale.format(
+                "alert.fixed",
+                &[("branch", &build.branch), ("build", &build_link)],
+            ))
+            .allowed_mentions(CreateAllowedMentions::new()),
+    };
+
+    let message = message.reference_message((channel_id, message_id));
+
+    if let Err(e) = channel_id.send_message(&app.serenity_http, message).await {
+        error!("Failed to send build notification: {e}");
+    }
+}
+
+/// Describes how long a branch has been broken, or how long it was broken for once it's fixed,
+/// given the failed builds that came before this one and the latest completed build
+pub fn streak_summary(
+    locale: Locale,
+    build: &Build,
+    streak: &[Build],
+    previous: Option<&Build>,
+) -> Option<String> {
+    let Some(first) = streak.first() else {
+        // A failure that breaks a green branch is the one most worth pointing out
+        return previous
+            .filter(|previous| is_failed(build) && is_success(previous))
+            .map(|previous| {
+                locale.format(
+                    "streak.first_failure",
+                    &[("number", &previous.run_number), ("url", &previous.url)],
+                )
+            });
+    };
+    let now = build.completed_at.unwrap_or(build.started_at);
+    // Minutes are precise enough for how long a branch has been broken
+    let broken_for = format_duration(locale, (now - first.started_at).num_seconds() / 60 * 60);
+    let broken_for = if broken_for.is_empty() {
+        locale.text("duration.under_a_minute")
+    } else {
+        broken_for
+    };
+
+    if is_failed(build) {
+        let builds = locale.plural("streak.builds", streak.len() as i64 + 1, &[]);
+        let first_commit = first
+            .commits
+            .iter()
+            .find(|commit| commit.id == first.head_sha)
+            .or(first.commits.last())
+            .map(|commit| {
+                locale.format(
+                    "streak.first_commit",
+                    &[
+                        ("sha", &commit.id.chars().take(7).collect::<String>()),
+                        ("url", &commit.url),
+                        (
+                            "author",
+                            &commit
+                                .author
+                                .username
+                                .as_deref()
+                                .unwrap_or(&commit.author.name),
+                        ),
+                    ],
+                )
+            })
+            .unwrap_or_default();
+
+        Some(locale.format(
+            "streak.broken_since",
+            &[
+                ("number", &first.run_number),
+                ("url", &first.url),
+                ("builds", &builds),
+                ("duration", &broken_for),
+                ("first_commit", &first_commit),
+            ],
+        ))
+    } else if is_success(build) {
+        Some(locale.plural(
+            "streak.fixed_after",
+            streak.len() as i64,
+            &[("duration", &broken_for)],
+        ))
+    } else {
+        None
+    }
+}
+
+fn is_success(build: &Build) -> bool {
+    build.conclusion.as_deref() == Some("success")
+}
+
+fn is_failed(build: &Build) -> bool {
+    matches!(
+        build.conclusion.as_deref(),
+        Some("failure") | Some("timed_out")
+    )
+}
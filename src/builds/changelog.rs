@@ -0,0 +1,232 @@
+use crate::builds::commits::{CommitInfo, CommitUser};
+use crate::config::repo_config::ChangelogSettings;
+use crate::i18n::Locale;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CONVENTIONAL_COMMIT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?<type>[a-zA-Z]+)(?:\((?<scope>[^)]*)\))?(?<breaking>!)?:\s*(?<description>.+)$")
+        .unwrap()
+});
+
+const BREAKING_SECTION: &str = "breaking";
+const OTHER_SECTION: &str = "other";
+
+/// Sections in the order they're rendered, their default titles are translated as `changelog.{id}`
+const SECTIONS: &[&str] = &[
+    BREAKING_SECTION,
+    "feat",
+    "fix",
+    "perf",
+    "refactor",
+    "revert",
+    "docs",
+    "style",
+    "test",
+    "build",
+    "ci",
+    "chore",
+    OTHER_SECTION,
+];
+
+struct ConventionalCommit<'a> {
+    r#type: String,
+    scope: Option<&'a str>,
+    breaking: bool,
+    description: &'a str,
+}
+
+impl<'a> ConventionalCommit<'a> {
+    fn parse(message: &'a str) -> Option<Self> {
+        let title = message.lines().next()?;
+        let captures = CONVENTIONAL_COMMIT.captures(title)?;
+
+        let breaking = captures.name("breaking").is_some()
+            || message.lines().any(|line| {
+                line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+            });
+
+        Some(Self {
+            r#type: captures["type"].to_lowercase(),
+            scope: captures
+                .name("scope")
+                .map(|s| s.as_str())
+                .filter(|s| !s.is_empty()),
+            breaking,
+            description: captures.name("description")?.as_str(),
+        })
+    }
+}
+
+/// Renders the commit list for a build embed, either as a flat list of commit titles or,
+/// when a changelog is configured, grouped into conventional commit sections.
+pub fn format_commits(
+    locale: Locale,
+    commits: &[CommitInfo],
+    changelog: Option<&ChangelogSettings>,
+) -> String {
+    let Some(changelog) = changelog else {
+        return commits
+            .iter()
+            .map(|commit| {
+                let title = commit.message.split("\n").next().unwrap_or_default();
+                format_line(commit, title, true)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+    };
+
+    let mut sections: Vec<(&str, Vec<String>)> = Vec::new();
+
+    for commit in commits {
+        let title = commit.message.split("\n").next().unwrap_or_default();
+
+        let (section, line) = match ConventionalCommit::parse(&commit.message) {
+            Some(parsed) => {
+                let hidden = changelog.is_hidden(&parsed.r#type);
+
+                // Breaking changes are always shown, even when their type is hidden
+                if hidden && !parsed.breaking {
+                    continue;
+                }
+
+                let description = match parsed.scope {
+                    Some(scope) => format!("**{scope}:** {}", parsed.description),
+                    None => parsed.description.to_string(),
+                };
+
+                let section = if parsed.breaking {
+                    BREAKING_SECTION
+                } else {
+                    SECTIONS
+                        .iter()
+                        .copied()
+                        .find(|id| *id == parsed.r#type)
+                        .unwrap_or(OTHER_SECTION)
+                };
+
+                (
+                    section,
+                    format_line(commit, &description, changelog.authors),
+                )
+            }
+            None if changelog.is_hidden(OTHER_SECTION) => continue,
+            None => (OTHER_SECTION, format_line(commit, title, changelog.authors)),
+        };
+
+        match sections.iter_mut().find(|(id, _)| *id == section) {
+            Some((_, lines)) => lines.push(line),
+            None => sections.push((section, vec![line])),
+        }
+    }
+
+    SECTIONS
+        .iter()
+        .filter_map(|id| {
+            let (_, lines) = sections.iter().find(|(section, _)| section == id)?;
+            let title = changelog
+                .titles
+                .iter()
+                .find(|(r#type, _)| r#type.eq_ignore_ascii_case(id))
+                .map(|(_, title)| title.clone())
+                .unwrap_or_else(|| locale.text(&format!("changelog.{id}")));
+
+            Some(format!("**{title}**\n{}", lines.join("\n")))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_line(commit: &CommitInfo, title: &str, credit: bool) -> String {
+    let title = title.replace("#", "\\#");
+
+    if credit {
+        format!(
+            "[➤]({}) {} - {}",
+            commit.url,
+            title,
+            format_contributors(commit)
+        )
+    } else {
+        format!("[➤]({}) {}", commit.url, title)
+    }
+}
+
+fn format_contributors(commit: &CommitInfo) -> String {
+    let contributors = commit
+        .contributors()
+        .iter()
+        .map(format_user)
+        .collect::<Vec<String>>();
+
+    match contributors.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} & {}", rest.join(", "), last),
+        _ => contributors.join(""),
+    }
+}
+
+fn format_user(user: &CommitUser) -> String {
+    match (user.bot_slug(), &user.username) {
+        (Some(slug), _) => format!("[{}](https://github.com/apps/{slug})", user.name),
+        (None, Some(username)) => format!("[{username}](https://github.com/{username})"),
+        (None, None) => user.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_type_scope_and_description() {
+        let commit = ConventionalCommit::parse("feat(ui): add a build button").unwrap();
+
+        assert_eq!(commit.r#type, "feat");
+        assert_eq!(commit.scope, Some("ui"));
+        assert!(!commit.breaking);
+        assert_eq!(commit.description, "add a build button");
+    }
+
+    #[test]
+    fn lowercases_the_type_and_drops_an_empty_scope() {
+        let commit = ConventionalCommit::parse("Fix(): handle missing jobs").unwrap();
+
+        assert_eq!(commit.r#type, "fix");
+        assert_eq!(commit.scope, None);
+    }
+
+    #[test]
+    fn detects_breaking_changes() {
+        assert!(
+            ConventionalCommit::parse("refactor!: drop the old API")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("feat: new config\n\nBREAKING CHANGE: `id` is gone")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("feat: new config\n\nBREAKING-CHANGE: `id` is gone")
+                .unwrap()
+                .breaking
+        );
+    }
+
+    #[test]
+    fn only_reads_the_title() {
+        let commit = ConventionalCommit::parse("fix: first line\n\nfeat: not a title").unwrap();
+
+        assert_eq!(commit.r#type, "fix");
+        assert_eq!(commit.description, "first line");
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        assert!(ConventionalCommit::parse("Update README.md").is_none());
+        assert!(ConventionalCommit::parse("feat:").is_none());
+        assert!(ConventionalCommit::parse("feat 2: numbers").is_none());
+        assert!(ConventionalCommit::parse("").is_none());
+    }
+}
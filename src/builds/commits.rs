@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+/// Commit message markers that keep a push's commits from being remembered for its builds
+pub const SKIP_PATTERNS: &[&str] = &[
+    "[skip ci]",
+    "[ci skip]",
+    "[no ci]",
+    "[skip actions]",
+    "[actions skip]",
+];
+
+const CO_AUTHOR_TRAILER: &str = "co-authored-by:";
+const NOREPLY_DOMAIN: &str = "@users.noreply.github.com";
+const BOT_SUFFIX: &str = "[bot]";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommitInfo {
+    pub id: String,
+    pub url: String,
+    pub message: String,
+    pub author: CommitUser,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommitUser {
+    pub name: String,
+    /// Only used to tell people apart, it's kept out of the history and its public API
+    #[serde(skip_serializing)]
+    pub email: Option<String>,
+    pub username: Option<String>,
+}
+
+impl CommitInfo {
+    /// The commit author followed by everyone credited through `Co-authored-by` trailers,
+    /// with duplicate people and bot accounts only listed once.
+    pub fn contributors(&self) -> Vec<CommitUser> {
+        let co_authors = self.message.lines().filter_map(|line| {
+            let line = line.trim();
+            let prefix = line.get(..CO_AUTHOR_TRAILER.len())?;
+
+            if prefix.eq_ignore_ascii_case(CO_AUTHOR_TRAILER) {
+                CommitUser::from_trailer(&line[CO_AUTHOR_TRAILER.len()..])
+            } else {
+                None
+            }
+        });
+
+        let mut contributors: Vec<CommitUser> = Vec::new();
+
+        for user in std::iter::once(self.author.clone()).chain(co_authors) {
+            if !contributors
+                .iter()
+                .any(|existing| existing.is_same_as(&user))
+            {
+                contributors.push(user);
+            }
+        }
+
+        contributors
+    }
+}
+
+impl CommitUser {
+    pub fn new(name: Option<String>, email: Option<String>, username: Option<String>) -> Self {
+        // Emails like `12345+login@users.noreply.github.com` tell us the GitHub login even
+        // when the payload doesn't
+        let username = username.or_else(|| {
+            let local = email.as_deref()?.strip_suffix(NOREPLY_DOMAIN)?;
+            let login = local.split_once('+').map_or(local, |(_, login)| login);
+            Some(login.to_string())
+        });
+
+        Self {
+            name: name
+                .filter(|name| !name.is_empty())
+                .or_else(|| username.clone())
+                .unwrap_or_else(|| "Unknown".into()),
+            email,
+            username,
+        }
+    }
+
+    /// Parses the value of a `Co-authored-by` trailer, e.g. `Jane Doe <jane@example.com>`
+    fn from_trailer(value: &str) -> Option<Self> {
+        let (name, email) = value.trim().split_once('<')?;
+        let email = email.trim().strip_suffix('>')?.trim();
+
+        Some(Self::new(
+            Some(name.trim().to_string()),
+            Some(email.to_string()),
+            None,
+        ))
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.username
+            .as_deref()
+            .unwrap_or(&self.name)
+            .ends_with(BOT_SUFFIX)
+    }
+
+    /// The app slug of a bot account, e.g. `dependabot` for `dependabot[bot]`
+    pub fn bot_slug(&self) -> Option<&str> {
+        self.username
+            .as_deref()
+            .unwrap_or(&self.name)
+            .strip_suffix(BOT_SUFFIX)
+    }
+
+    fn is_same_as(&self, other: &CommitUser) -> bool {
+        if self.is_bot() || other.is_bot() {
+            return self.bot_slug().map(str::to_lowercase)
+                == other.bot_slug().map(str::to_lowercase);
+        }
+
+        let matches = |a: &Option<String>, b: &Option<String>| matches!((a, b), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b));
+
+        matches(&self.username, &other.username)
+            || matches(&self.email, &other.email)
+            || self.name.eq_ignore_ascii_case(&other.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(message: &str, author: CommitUser) -> CommitInfo {
+        CommitInfo {
+            id: "abc".into(),
+            url: String::new(),
+            message: message.into(),
+            author,
+        }
+    }
+
+    #[test]
+    fn reads_the_login_from_noreply_emails() {
+        let user = CommitUser::new(
+            Some("Jane Doe".into()),
+            Some("12345+janedoe@users.noreply.github.com".into()),
+            None,
+        );
+        assert_eq!(user.username.as_deref(), Some("janedoe"));
+
+        let user = CommitUser::new(None, Some("janedoe@users.noreply.github.com".into()), None);
+        assert_eq!(user.username.as_deref(), Some("janedoe"));
+        assert_eq!(user.name, "janedoe");
+    }
+
+    #[test]
+    fn prefers_the_given_username() {
+        let user = CommitUser::new(
+            Some("Jane".into()),
+            Some("1+other@users.noreply.github.com".into()),
+            Some("janedoe".into()),
+        );
+
+        assert_eq!(user.username.as_deref(), Some("janedoe"));
+    }
+
+    #[test]
+    fn falls_back_to_unknown_without_a_name() {
+        let user = CommitUser::new(Some(String::new()), Some("jane@example.com".into()), None);
+
+        assert_eq!(user.name, "Unknown");
+        assert_eq!(user.username, None);
+    }
+
+    #[test]
+    fn lists_co_authors_after_the_author() {
+        let author = CommitUser::new(Some("Jane".into()), Some("jane@example.com".into()), None);
+        let commit = commit(
+            "feat: pair programming\n\n\
+             Co-authored-by: John Smith <john@example.com>\n\
+             co-authored-by: Ann <1+ann@users.noreply.github.com>",
+            author,
+        );
+
+        let contributors = commit.contributors();
+
+        assert_eq!(
+            contributors
+                .iter()
+                .map(|user| user.name.as_str())
+                .collect::<Vec<_>>(),
+            ["Jane", "John Smith", "Ann"]
+        );
+        assert_eq!(contributors[2].username.as_deref(), Some("ann"));
+    }
+
+    #[test]
+    fn lists_everyone_once() {
+        let author = CommitUser::new(
+            Some("Jane".into()),
+            Some("jane@example.com".into()),
+            Some("janedoe".into()),
+        );
+        let commit = commit(
+            "fix: typo\n\n\
+             Co-authored-by: Jane Doe <JANE@example.com>\n\
+             Co-authored-by: dependabot[bot] <49699333+dependabot[bot]@users.noreply.github.com>\n\
+             Co-authored-by: Dependabot[bot] <support@github.com>",
+            author,
+        );
+
+        let contributors = commit.contributors();
+
+        assert_eq!(contributors.len(), 2);
+        assert_eq!(contributors[1].bot_slug(), Some("dependabot"));
+    }
+
+    #[test]
+    fn ignores_malformed_trailers() {
+        let author = CommitUser::new(Some("Jane".into()), None, None);
+        let commit = commit(
+            "fix: typo\n\nCo-authored-by: John Smith\nCo-authored-by: <broken",
+            author,
+        );
+
+        assert_eq!(commit.contributors().len(), 1);
+    }
+
+    #[test]
+    fn keeps_emails_out_of_serialized_commits() {
+        let author = CommitUser::new(Some("Jane".into()), Some("jane@example.com".into()), None);
+        let json = serde_json::to_string(&commit("fix: typo", author)).unwrap();
+
+        assert!(!json.contains("jane@example.com"));
+        assert!(serde_json::from_str::<CommitInfo>(&json).is_ok());
+    }
+}
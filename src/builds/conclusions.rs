@@ -0,0 +1,188 @@
+use crate::app::App;
+use crate::config::repo_config::{ConclusionSettings, DeleteMode, RepoSettings};
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::{Build, BuildStatus};
+use crate::i18n::Locale;
+use serde::{Deserialize, Serialize};
+use serenity::all::colours::{branding, roles};
+use serenity::all::{ChannelId, Colour, EmojiId, MessageId};
+use snafu::ResultExt;
+use tracing::log::error;
+
+/// Builds looked through to find a newer run of the same workflow
+const SUPERSEDED_LOOKBACK: u32 = 10;
+
+/// Conclusion of a finished run, named the way GitHub names them. Statuses of other forges are
+/// mapped onto these, and octocrab's `Conclusion` has no `stale` and fails to deserialize it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RunConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Stale,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Status line and embed color for a finished run, with the repository's overrides applied
+pub async fn run_status(
+    app: &App,
+    locale: Locale,
+    conclusion: Option<RunConclusion>,
+    settings: Option<&ConclusionSettings>,
+) -> Result<(String, Colour), Error> {
+    let (emoji, color) = match conclusion {
+        Some(RunConclusion::Success) => (
+            application_emoji(app, app.emojis.success).await?,
+            branding::GREEN,
+        ),
+        Some(RunConclusion::Failure) => (
+            application_emoji(app, app.emojis.failed).await?,
+            branding::RED,
+        ),
+        Some(RunConclusion::TimedOut) => ("⏱️".into(), roles::ORANGE),
+        Some(RunConclusion::Cancelled) => ("🚫".into(), roles::LIGHTER_GREY),
+        Some(RunConclusion::Skipped) => ("⏭️".into(), roles::LIGHTER_GREY),
+        Some(RunConclusion::Neutral) => ("➖".into(), branding::BLURPLE),
+        Some(RunConclusion::ActionRequired) => ("⚠️".into(), branding::YELLOW),
+        Some(RunConclusion::Stale) => ("🕸️".into(), roles::DARK_GREY),
+        Some(RunConclusion::Unknown) | None => ("❔".into(), roles::DEFAULT),
+    };
+
+    let emoji = settings
+        .and_then(|settings| settings.emoji.clone())
+        .unwrap_or(emoji);
+    let label = settings
+        .and_then(|settings| settings.label.clone())
+        .unwrap_or_else(|| conclusion_label(locale, conclusion.as_ref()));
+    let color = settings
+        .and_then(|settings| settings.color)
+        .map(Colour::new)
+        .unwrap_or(color);
+
+    Ok((format!("{emoji} {label}"), color))
+}
+
+/// Translated name of a conclusion, as stored in the build history or reported by GitHub
+pub fn conclusion_label(locale: Locale, conclusion: Option<&RunConclusion>) -> String {
+    let key = match conclusion {
+        Some(RunConclusion::Success) => "conclusion.success",
+        Some(RunConclusion::Failure) => "conclusion.failure",
+        Some(RunConclusion::TimedOut) => "conclusion.timed_out",
+        Some(RunConclusion::Cancelled) => "conclusion.cancelled",
+        Some(RunConclusion::Skipped) => "conclusion.skipped",
+        Some(RunConclusion::Neutral) => "conclusion.neutral",
+        Some(RunConclusion::ActionRequired) => "conclusion.action_required",
+        Some(RunConclusion::Stale) => "conclusion.stale",
+        Some(RunConclusion::Unknown) => "conclusion.unknown",
+        None => "conclusion.unfinished",
+    };
+
+    locale.text(key)
+}
+
+/// Whether the repository wants the message of this finished build removed
+pub async fn should_delete(
+    app: &App,
+    build: &Build,
+    settings: Option<&ConclusionSettings>,
+) -> bool {
+    match settings.map(|settings| &settings.delete) {
+        Some(DeleteMode::Always) => true,
+        Some(DeleteMode::Superseded) => is_superseded(app, build).await,
+        Some(DeleteMode::Never) | None => false,
+    }
+}
+
+async fn is_superseded(app: &App, build: &Build) -> bool {
+    app.history
+        .builds(&build.repository, Some(&build.branch), SUPERSEDED_LOOKBACK)
+        .await
+        .inspect_err(|e| error!("Failed to look for newer builds: {e}"))
+        .unwrap_or_default()
+        .iter()
+        .any(|newer| {
+            newer.workflow == build.workflow
+                && newer.run_id != build.run_id
+                && newer.started_at > build.started_at
+        })
+}
+
+/// Deletes the messages of older finished builds that were waiting for a newer run, for when the
+/// newer run is recorded only after they finished
+pub async fn delete_superseded(
+    app: &App,
+    config: &RepoSettings,
+    channel_id: ChannelId,
+    build: &Build,
+) {
+    let waits_for_newer = |conclusion: &str| {
+        serde_json::from_value::<RunConclusion>(serde_json::Value::String(conclusion.into()))
+            .ok()
+            .and_then(|conclusion| config.conclusions.get(&conclusion))
+            .is_some_and(|settings| settings.delete == DeleteMode::Superseded)
+    };
+
+    if !config
+        .conclusions
+        .values()
+        .any(|settings| settings.delete == DeleteMode::Superseded)
+    {
+        return;
+    }
+
+    let older = app
+        .history
+        .builds(&build.repository, Some(&build.branch), SUPERSEDED_LOOKBACK)
+        .await
+        .inspect_err(|e| error!("Failed to look for superseded builds: {e}"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|older| {
+            older.workflow == build.workflow
+                && older.run_id != build.run_id
+                && older.started_at < build.started_at
+                && older.status == BuildStatus::Completed
+                && older.conclusion.as_deref().is_some_and(waits_for_newer)
+        });
+
+    for older in older {
+        let Some(message_id) = older.message_id else {
+            continue;
+        };
+
+        // Gone either way, so the build is recorded without its message even when this fails
+        if let Err(e) = channel_id
+            .delete_message(&app.serenity_http, MessageId::new(message_id))
+            .await
+        {
+            error!("Failed to delete superseded build message: {e}");
+        }
+
+        let older = Build {
+            message_id: None,
+            message_link: None,
+            ..older
+        };
+
+        if let Err(e) = app.history.record(older).await {
+            error!("Failed to record build: {e}");
+        }
+    }
+}
+
+pub async fn application_emoji(app: &App, emoji: EmojiId) -> Result<String, Error> {
+    let emoji = app
+        .serenity_http
+        .get_application_emoji(emoji)
+        .await
+        .context(FailedToFindEmojiSnafu)?;
+
+    Ok(emoji.to_string())
+}
@@ -0,0 +1,562 @@
+use crate::app::{App, Forge};
+use crate::builds::alerts::notify_transition;
+use crate::builds::conclusions::{application_emoji, delete_superseded};
+use crate::builds::threads::{open_thread, post_in_thread};
+use crate::config::repo_config::{RepoSettings, ThreadMode, ThreadSettings};
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::{Build, BuildLink};
+use crate::i18n::Locale;
+use crate::util::time::format_duration;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde_json::Value;
+use serenity::all::colours::branding;
+use serenity::all::*;
+use snafu::ResultExt;
+use tracing::log::{error, warn};
+
+/// Discord's limit for an embed's description
+const DESCRIPTION_LIMIT: usize = 4096;
+/// Discord's limit for all text in an embed, counting its title, description, fields, author and
+/// footer
+const EMBED_LIMIT: usize = 6000;
+/// Discord's limit for the number of fields on an embed
+const FIELDS_LIMIT: usize = 25;
+/// Discord's limit for a field's name
+const FIELD_NAME_LIMIT: usize = 256;
+/// Discord's limit for a field's value
+const FIELD_VALUE_LIMIT: usize = 1024;
+
+/// Where a build's messages are posted and how they read
+#[derive(Clone, Copy)]
+pub struct BuildChannel<'a> {
+    pub forge: Forge,
+    pub config: &'a RepoSettings,
+    pub locale: Locale,
+    pub channel_id: ChannelId,
+}
+
+/// Everything shown on a build message, replacing whatever an earlier attempt left on it
+pub struct BuildMessage {
+    pub embed: CreateEmbed,
+    pub components: Vec<CreateActionRow>,
+    pub attachments: Vec<CreateAttachment>,
+}
+
+/// What every message of a build shows, whichever forge it ran on
+pub struct BuildEmbed {
+    pub locale: Locale,
+    /// Names the project and branch, linking to the run
+    pub author: CreateEmbedAuthor,
+    /// Who started the run
+    pub footer: Option<CreateEmbedFooter>,
+    pub started_at: DateTime<Utc>,
+    pub version: String,
+    pub commits: String,
+    /// Shown instead of the commits when they don't fit
+    pub commits_link: Option<String>,
+}
+
+/// The final message of a build, along with what's posted in its thread
+pub struct FinalMessage<'a> {
+    /// Message the build was shown as running in, a new one is posted without it
+    pub message_id: Option<MessageId>,
+    pub message: BuildMessage,
+    /// Repeated in the thread, so it reads as a log of the build
+    pub status: String,
+    pub threads: Option<&'a ThreadSettings>,
+    /// Posted in the thread, or below the message when the thread couldn't be opened
+    pub follow_up: Option<CreateMessage>,
+}
+
+impl BuildEmbed {
+    /// The embed with its status and the lines shown between the version and the commits
+    pub fn embed(&self, status: &str, extra: &str) -> CreateEmbed {
+        self.fit(status, extra, Vec::new())
+    }
+
+    /// Same as [`Self::embed`] with fields below the description, leaving out what doesn't fit
+    pub fn fit(&self, status: &str, extra: &str, fields: Vec<(String, String)>) -> CreateEmbed {
+        fit_embed(
+            |extra, commits| self.with_commits(status, extra, commits),
+            extra,
+            &self.commits,
+            self.commits_link.as_deref().unwrap_or(&self.commits),
+            fields,
+        )
+    }
+
+    fn with_commits(&self, status: &str, extra: &str, commits: &str) -> CreateEmbed {
+        let locale = self.locale;
+        let description = format!(
+            r"
+            ## {} <t:{}:R>
+            {}: {}
+            {}: **{}**
+            {}{}",
+            locale.text("build.heading"),
+            self.started_at.timestamp(),
+            locale.text("build.status"),
+            status,
+            locale.text("build.version"),
+            self.version,
+            extra,
+            commits
+        );
+
+        let mut embed = CreateEmbed::new()
+            .author(self.author.clone())
+            .description(description)
+            .color(branding::BLURPLE);
+
+        if let Some(footer) = &self.footer {
+            embed = embed.footer(footer.clone());
+        }
+
+        embed
+    }
+}
+
+/// Status of a build that's running, with the processing emoji
+pub async fn running_status(
+    app: &App,
+    locale: Locale,
+    run_attempt: i64,
+    run_number: i64,
+) -> Result<String, Error> {
+    let emoji = application_emoji(app, app.emojis.processing).await?;
+    let status = if run_attempt > 1 {
+        locale.format(
+            "build.attempt_running",
+            &[("attempt", &run_attempt), ("number", &run_number)],
+        )
+    } else {
+        locale.format("build.running", &[("number", &run_number)])
+    };
+
+    Ok(format!("{status} {emoji}"))
+}
+
+/// Status of a build that's done, with how long it ran for
+pub fn finished_status(
+    locale: Locale,
+    run_status: &str,
+    run_attempt: i64,
+    run_number: i64,
+    duration: i64,
+) -> String {
+    let attempt = if run_attempt > 1 {
+        locale.format("build.attempt", &[("attempt", &run_attempt)])
+    } else {
+        String::new()
+    };
+
+    locale.format(
+        "build.finished",
+        &[
+            ("status", &run_status),
+            ("number", &run_number),
+            ("attempt", &attempt),
+            ("duration", &format_duration(locale, duration)),
+        ],
+    )
+}
+
+/// Shows the build as running on its message, then records it. Returns the message's id.
+pub async fn announce_running(
+    app: &App,
+    channel: BuildChannel<'_>,
+    message_id: Option<MessageId>,
+    embed: CreateEmbed,
+    build: Build,
+) -> Result<MessageId, Error> {
+    let BuildChannel {
+        forge,
+        config,
+        locale,
+        channel_id,
+    } = channel;
+    let (message_id, _) = edit_or_send(
+        app,
+        channel_id,
+        message_id,
+        BuildMessage {
+            embed,
+            components: Vec::new(),
+            attachments: Vec::new(),
+        },
+    )
+    .await?;
+
+    let build = Build {
+        message_link: Some(app.message_link(channel_id, message_id).await),
+        message_id: Some(message_id.get()),
+        ..build
+    };
+    let name = format!("{} #{}", build.branch, build.run_number);
+    let run = (forge, build.run_id);
+
+    delete_superseded(app, config, channel_id, &build).await;
+
+    if let Err(e) = app.history.record(build).await {
+        error!("Failed to record build: {e}");
+    }
+
+    if let Some(threads) = config
+        .threads
+        .as_ref()
+        .filter(|threads| threads.mode == ThreadMode::All)
+    {
+        open_thread(app, channel_id, message_id, run, &name, threads, locale).await;
+    }
+
+    Ok(message_id)
+}
+
+/// Shows the build's final message, posts in its thread and mentions whoever asked to be told,
+/// then records it. Returns the recorded build and whether its message was newly posted.
+pub async fn finish_build(
+    app: &App,
+    channel: BuildChannel<'_>,
+    message: FinalMessage<'_>,
+    build: Build,
+    previous: Option<&Build>,
+) -> Result<(Build, bool), Error> {
+    let BuildChannel {
+        forge,
+        config,
+        locale,
+        channel_id,
+    } = channel;
+    let (message_id, sent) =
+        edit_or_send(app, channel_id, message.message_id, message.message).await?;
+
+    if let Some(threads) = message.threads {
+        let name = format!("{} #{}", build.branch, build.run_number);
+        let run = (forge, build.run_id);
+        let thread = open_thread(app, channel_id, message_id, run, &name, threads, locale).await;
+
+        match thread {
+            Some(thread) => {
+                if let Some(follow_up) = message.follow_up {
+                    post_in_thread(app, thread, follow_up).await;
+                }
+
+                post_in_thread(app, thread, CreateMessage::new().content(message.status)).await;
+            }
+            // Don't lose the log just because the thread couldn't be opened
+            None => {
+                if let Some(follow_up) = message.follow_up {
+                    post_in_thread(app, channel_id, follow_up).await;
+                }
+            }
+        }
+    }
+
+    let build = Build {
+        message_link: Some(app.message_link(channel_id, message_id).await),
+        message_id: Some(message_id.get()),
+        ..build
+    };
+
+    if !config.mentions.is_empty() {
+        notify_transition(
+            app,
+            locale,
+            &config.mentions,
+            channel_id,
+            message_id,
+            &build,
+            previous,
+        )
+        .await;
+    }
+
+    delete_superseded(app, config, channel_id, &build).await;
+
+    if let Err(e) = app.history.record(build.clone()).await {
+        error!("Failed to record build: {e}");
+    }
+
+    Ok((build, sent))
+}
+
+/// The repository's buttons with their variables filled in
+pub fn button_row(config: &RepoSettings, variables: &[(&str, &str)]) -> CreateActionRow {
+    let buttons = config
+        .buttons
+        .iter()
+        .map(|(id, button)| button.convert(id, &button.resolve_url(variables)))
+        .collect();
+
+    CreateActionRow::Buttons(buttons)
+}
+
+/// Links of the repository's link buttons, kept with the build in the history
+pub fn build_links(config: &RepoSettings, variables: &[(&str, &str)]) -> Vec<BuildLink> {
+    config
+        .buttons
+        .iter()
+        .filter(|(_, button)| button.is_link())
+        .map(|(id, button)| BuildLink {
+            label: button.label.clone().unwrap_or_else(|| id.clone()),
+            url: button.resolve_url(variables),
+        })
+        .collect()
+}
+
+/// Edits the build's message, or posts a new one when there is none or it has been deleted.
+/// Returns the message's id and whether it was newly posted.
+pub async fn edit_or_send(
+    app: &App,
+    channel_id: ChannelId,
+    message_id: Option<MessageId>,
+    message: BuildMessage,
+) -> Result<(MessageId, bool), Error> {
+    if let Some(message_id) = message_id {
+        let mut edit = EditMessage::new()
+            .embed(message.embed.clone())
+            .components(message.components.clone())
+            .allowed_mentions(CreateAllowedMentions::new());
+
+        edit = if message.attachments.is_empty() {
+            edit.remove_all_attachments()
+        } else {
+            message
+                .attachments
+                .iter()
+                .cloned()
+                .fold(edit, |edit, attachment| edit.new_attachment(attachment))
+        };
+
+        match channel_id
+            .edit_message(&app.serenity_http, message_id, edit)
+            .await
+        {
+            Ok(_) => return Ok((message_id, false)),
+            // Someone deleted the message, or it was deleted for its conclusion
+            Err(serenity::Error::Http(e)) if e.status_code() == Some(StatusCode::NOT_FOUND) => {
+                warn!("Build message {message_id} is gone, posting a new one");
+            }
+            Err(e) => return Err(e).context(FailedToSendMessageSnafu),
+        }
+    }
+
+    let sent = channel_id
+        .send_message(
+            &app.serenity_http,
+            CreateMessage::new()
+                .embed(message.embed)
+                .components(message.components)
+                .add_files(message.attachments)
+                .allowed_mentions(CreateAllowedMentions::new()),
+        )
+        .await
+        .context(FailedToSendMessageSnafu)?;
+
+    Ok((sent.id, true))
+}
+
+/// Builds the final embed of a build so Discord accepts it. Fields are clamped to their limits
+/// and given up last first while the embed is too long, a description that's too long is cut
+/// short, first in the extra lines, then by replacing the commit list with a link to the commits.
+pub fn fit_embed(
+    embed: impl Fn(&str, &str) -> CreateEmbed,
+    extra: &str,
+    commits: &str,
+    commits_link: &str,
+    fields: Vec<(String, String)>,
+) -> CreateEmbed {
+    let mut extra = extra.to_string();
+    let mut commits = commits.to_string();
+    let mut fields = fields
+        .into_iter()
+        .take(FIELDS_LIMIT)
+        .map(|(name, value)| {
+            (
+                truncate(&name, FIELD_NAME_LIMIT),
+                truncate(&value, FIELD_VALUE_LIMIT),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    loop {
+        let candidate = embed(&extra, &commits).fields(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone(), false)),
+        );
+        let (description, total) = embed_length(&candidate);
+
+        let excess = match (
+            description.saturating_sub(DESCRIPTION_LIMIT),
+            total.saturating_sub(EMBED_LIMIT),
+        ) {
+            (0, 0) => return candidate,
+            // Fields don't count towards the description, so they only make room in the embed
+            (0, _) if fields.pop().is_some() => continue,
+            (0, excess) => excess,
+            (excess, _) => excess,
+        };
+
+        if !extra.is_empty() {
+            extra = cut(&extra, excess);
+        } else if commits != commits_link {
+            commits = commits_link.to_string();
+        } else if !commits.is_empty() {
+            commits = cut(&commits, excess);
+        } else {
+            return candidate;
+        }
+    }
+}
+
+/// The text with at least `excess` characters cut off its end, marked by an ellipsis
+fn cut(text: &str, excess: usize) -> String {
+    // Room for the ellipsis and line break that replace what's cut
+    match text.chars().count().saturating_sub(excess + 2) {
+        0 => String::new(),
+        kept => text.chars().take(kept).collect::<String>() + "…\n",
+    }
+}
+
+/// The text cut short to at most `limit` characters
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+
+    text.chars().take(limit - 1).collect::<String>() + "…"
+}
+
+/// Characters in an embed's description and in all of its text, as Discord counts them
+fn embed_length(embed: &CreateEmbed) -> (usize, usize) {
+    let embed = serde_json::to_value(embed).unwrap_or_default();
+    let length = |value: &Value| value.as_str().map_or(0, |text| text.chars().count());
+
+    let description = length(&embed["description"]);
+    let fields = embed["fields"]
+        .as_array()
+        .map(|fields| {
+            fields
+                .iter()
+                .map(|field| length(&field["name"]) + length(&field["value"]))
+                .sum::<usize>()
+        })
+        .unwrap_or_default();
+    let total = description
+        + fields
+        + length(&embed["title"])
+        + length(&embed["author"]["name"])
+        + length(&embed["footer"]["text"]);
+
+    (description, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "[Commits](https://example.com)";
+
+    fn embed(extra: &str, commits: &str) -> CreateEmbed {
+        CreateEmbed::new()
+            .title("Build")
+            .description(format!("{extra}{commits}"))
+    }
+
+    fn json(embed: &CreateEmbed) -> Value {
+        serde_json::to_value(embed).unwrap()
+    }
+
+    fn description(embed: &CreateEmbed) -> String {
+        json(embed)["description"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn fields(count: usize, length: usize) -> Vec<(String, String)> {
+        (0..count)
+            .map(|index| (format!("Field {index}"), "x".repeat(length)))
+            .collect()
+    }
+
+    #[test]
+    fn leaves_an_embed_that_fits_alone() {
+        let fitted = fit_embed(embed, "extra\n", "commits", LINK, fields(2, 10));
+
+        assert_eq!(description(&fitted), "extra\ncommits");
+        assert_eq!(json(&fitted)["fields"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn clamps_fields_to_discords_limits() {
+        let mut many = fields(FIELDS_LIMIT + 5, 10);
+        many[0] = ("n".repeat(300), "v".repeat(2000));
+
+        let fitted = json(&fit_embed(embed, "", "commits", LINK, many));
+        let fields = fitted["fields"].as_array().unwrap();
+
+        assert_eq!(fields.len(), FIELDS_LIMIT);
+
+        let name = fields[0]["name"].as_str().unwrap();
+        let value = fields[0]["value"].as_str().unwrap();
+        assert_eq!(name.chars().count(), FIELD_NAME_LIMIT);
+        assert_eq!(value.chars().count(), FIELD_VALUE_LIMIT);
+        assert!(value.ends_with('…'));
+    }
+
+    #[test]
+    fn gives_up_the_last_fields_while_the_embed_is_too_long() {
+        let fitted = fit_embed(embed, "extra\n", "commits", LINK, fields(10, 1000));
+        let (description_length, total) = embed_length(&fitted);
+
+        assert_eq!(description(&fitted), "extra\ncommits");
+        assert!(total <= EMBED_LIMIT);
+        assert!(description_length <= DESCRIPTION_LIMIT);
+
+        let fields = json(&fitted)["fields"].as_array().unwrap().clone();
+        assert_eq!(fields.len(), 5);
+        assert_eq!(fields[4]["name"], "Field 4");
+    }
+
+    #[test]
+    fn cuts_the_extra_lines_before_the_commits() {
+        let extra = "e".repeat(5000);
+        let fitted = fit_embed(embed, &extra, "commits", LINK, Vec::new());
+        let description = description(&fitted);
+
+        assert!(description.chars().count() <= DESCRIPTION_LIMIT);
+        assert!(description.ends_with("…\ncommits"));
+    }
+
+    #[test]
+    fn swaps_commits_that_dont_fit_for_the_link() {
+        let commits = "c".repeat(5000);
+        let fitted = fit_embed(embed, "extra\n", &commits, LINK, Vec::new());
+
+        // The extra lines are given up first, even when that alone can't make room
+        assert_eq!(description(&fitted), LINK);
+    }
+
+    #[test]
+    fn cuts_the_commits_without_a_link() {
+        let commits = "c".repeat(5000);
+        let fitted = fit_embed(embed, "", &commits, &commits, Vec::new());
+        let description = description(&fitted);
+
+        assert!(description.chars().count() <= DESCRIPTION_LIMIT);
+        assert!(description.starts_with("ccc"));
+        assert!(description.ends_with("…\n"));
+    }
+
+    #[test]
+    fn counts_characters_rather_than_bytes() {
+        let extra = "é".repeat(DESCRIPTION_LIMIT - 7);
+        let fitted = fit_embed(embed, &extra, "commits", LINK, Vec::new());
+
+        assert_eq!(description(&fitted), format!("{extra}commits"));
+    }
+}
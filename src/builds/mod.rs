@@ -0,0 +1,7 @@
+pub mod alerts;
+pub mod changelog;
+pub mod commits;
+pub mod conclusions;
+pub mod messages;
+pub mod threads;
+pub mod timing;
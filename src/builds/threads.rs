@@ -0,0 +1,51 @@
+use crate::app::{App, Forge};
+use crate::config::repo_config::ThreadSettings;
+use crate::i18n::Locale;
+use serenity::all::{ChannelId, CreateAllowedMentions, CreateMessage, CreateThread, MessageId};
+use tracing::log::error;
+
+/// Discord's limit for a thread name
+const THREAD_NAME_LIMIT: usize = 100;
+
+/// Opens a discussion thread on a build's message, reusing the one already opened for the run
+pub async fn open_thread(
+    app: &App,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    run: (Forge, u64),
+    name: &str,
+    settings: &ThreadSettings,
+    locale: Locale,
+) -> Option<ChannelId> {
+    if let Some((thread_id, _)) = app.cache.build_threads.get(&run).await {
+        return Some(thread_id);
+    }
+
+    let name = name.chars().take(THREAD_NAME_LIMIT).collect::<String>();
+    let thread = CreateThread::new(name).auto_archive_duration(settings.auto_archive);
+
+    match channel_id
+        .create_thread_from_message(&app.serenity_http, message_id, thread)
+        .await
+    {
+        Ok(thread) => {
+            app.cache
+                .build_threads
+                .insert(run, (thread.id, locale))
+                .await;
+            Some(thread.id)
+        }
+        Err(e) => {
+            error!("Failed to create build thread: {e}");
+            None
+        }
+    }
+}
+
+pub async fn post_in_thread(app: &App, thread_id: ChannelId, message: CreateMessage) {
+    let message = message.allowed_mentions(CreateAllowedMentions::new());
+
+    if let Err(e) = thread_id.send_message(&app.serenity_http, message).await {
+        error!("Failed to post in build thread: {e}");
+    }
+}
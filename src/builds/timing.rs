@@ -0,0 +1,82 @@
+use crate::app::App;
+use crate::history::store::Build;
+use crate::i18n::Locale;
+use crate::util::time::format_duration;
+use chrono::{DateTime, Utc};
+use tracing::log::error;
+
+/// Successful builds the usual run time is the median of
+const MEDIAN_WINDOW: u32 = 10;
+/// Fewer successful builds than this aren't enough to tell what's usual
+const MEDIAN_MINIMUM: usize = 3;
+/// Differences from the usual run time smaller than this aren't worth mentioning
+const COMPARISON_THRESHOLD: i64 = 30;
+
+/// How long a run waited for a runner and how long its jobs took after that, in seconds
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    pub queued: i64,
+    pub run: i64,
+}
+
+/// Median run time of the branch's recent successful builds of the workflow, if there are
+/// enough of them
+pub async fn usual_run_time(app: &App, build: &Build) -> Option<i64> {
+    let mut run_times = app
+        .history
+        .run_times(
+            &build.repository,
+            &build.branch,
+            &build.workflow,
+            build.run_id,
+            MEDIAN_WINDOW,
+        )
+        .await
+        .inspect_err(|e| error!("Failed to find previous run times: {e}"))
+        .ok()?;
+
+    if run_times.len() < MEDIAN_MINIMUM {
+        return None;
+    }
+
+    run_times.sort_unstable();
+    let middle = run_times.len() / 2;
+
+    Some(if run_times.len() % 2 == 0 {
+        (run_times[middle - 1] + run_times[middle]) / 2
+    } else {
+        run_times[middle]
+    })
+}
+
+/// Line with the time spent queued and how the run time compares to the usual one
+pub fn timing_summary(locale: Locale, timing: Timing, usual: Option<i64>) -> Option<String> {
+    let mut parts = Vec::new();
+
+    if timing.queued > 0 {
+        let queued = format_duration(locale, timing.queued);
+        parts.push(locale.format("timing.queued", &[("duration", &queued)]));
+    }
+
+    if let Some(usual) = usual {
+        let difference = timing.run - usual;
+        let duration = format_duration(locale, difference.abs());
+
+        if difference >= COMPARISON_THRESHOLD {
+            parts.push(locale.format("timing.slower", &[("duration", &duration)]));
+        } else if difference <= -COMPARISON_THRESHOLD {
+            parts.push(locale.format("timing.faster", &[("duration", &duration)]));
+        } else {
+            parts.push(locale.text("timing.as_usual"));
+        }
+    }
+
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// When a run that went in progress at the given time should be done, going by the usual run time
+pub fn estimated_completion(locale: Locale, started_at: DateTime<Utc>, usual: i64) -> String {
+    let timestamp = (started_at.timestamp() + usual).to_string();
+
+    locale.format("timing.eta", &[("timestamp", &timestamp)])
+}
@@ -6,6 +6,7 @@ use serenity::all::ChannelId;
 use snafu::{ResultExt, Whatever};
 use std::collections::HashMap;
 use std::fs;
+use std::num::NonZeroU32;
 use std::path::Path;
 use std::sync::Arc;
 use tracing::log::{error, info};
@@ -26,6 +27,31 @@ pub struct Config {
     pub channel_id: ChannelId,
     #[serde(default = "bool_true")]
     pub buttons: bool,
+    /// Language of the bot's messages in the channel, English by default
+    pub locale: Option<String>,
+    /// Keep copies of the artifacts of successful builds, which outlive GitHub's retention
+    pub mirror: Option<MirrorSettings>,
+    /// Show the builds of a private repository on the dashboard, feeds, badges and API anyway
+    #[serde(default)]
+    pub publish_private: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MirrorSettings {
+    /// Artifacts to mirror, every artifact of the build when empty
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Builds kept per branch, the oldest are removed first
+    pub keep: Option<NonZeroU32>,
+    /// Days a build's artifacts are kept for
+    pub max_age: Option<i64>,
+}
+
+impl MirrorSettings {
+    /// Whether the artifact is one of those mirrored
+    pub fn includes(&self, name: &str) -> bool {
+        self.artifacts.is_empty() || self.artifacts.iter().any(|artifact| artifact == name)
+    }
 }
 
 impl Config {
@@ -38,10 +64,49 @@ impl Config {
         }
     }
 
+    /// The `owner/repo` part of the repository url, or `group/project` on GitLab
+    pub fn full_name(&self) -> &str {
+        let url = self.repository_url.trim_end_matches('/');
+        let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+        url.split_once('/').map_or(url, |(_, path)| path)
+    }
+
+    /// Whether the url is the repository's, regardless of case and trailing slashes
+    pub fn matches_url(&self, url: &str) -> bool {
+        self.repository_url
+            .trim_end_matches('/')
+            .eq_ignore_ascii_case(url.trim_end_matches('/'))
+    }
+
     pub fn get() -> Guard<Arc<HashMap<String, Config>>> {
         CONFIG.load()
     }
 
+    /// Looks up a configured repository by its `owner/repo` name
+    pub fn find(full_name: &str) -> Option<Config> {
+        Self::get()
+            .values()
+            .find(|config| config.full_name().eq_ignore_ascii_case(full_name))
+            .cloned()
+    }
+
+    /// Looks up the configured repository a path starts with, returning it with the rest of the
+    /// path. GitLab projects can sit in subgroups, so the name can't be told from the slashes.
+    pub fn find_prefix(path: &str) -> Option<(Config, &str)> {
+        Self::get()
+            .values()
+            .filter_map(|config| {
+                let full_name = config.full_name();
+                let rest = path.get(full_name.len()..)?.strip_prefix('/')?;
+
+                path.get(..full_name.len())?
+                    .eq_ignore_ascii_case(full_name)
+                    .then(|| (config.clone(), rest))
+            })
+            .max_by_key(|(config, _)| config.full_name().len())
+    }
+
     fn load() -> Result<HashMap<String, Config>, Whatever> {
         let config_contents = fs::read_to_string("./config.yml")
             .or_else(|_| fs::read_to_string("/app/config.yml"))
@@ -1,17 +1,41 @@
+use crate::builds::conclusions::RunConclusion;
+use octocrab::models::webhook_events::payload::ReleaseWebhookEventAction;
+use regex::Regex;
 use serde::Deserialize;
-use serenity::all::{CreateButton, ReactionType};
+use serenity::all::{AutoArchiveDuration, ChannelId, CreateButton, ReactionType, RoleId, UserId};
 use std::collections::HashMap;
+use tracing::log::warn;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct RepoSettings {
     pub minecraft_version: String,
     pub workflows: Vec<String>,
     pub mod_version: ModVersionVariable,
     #[serde(default)]
     pub buttons: HashMap<String, Button>,
+    pub changelog: Option<ChangelogSettings>,
+    pub releases: Option<ReleaseSettings>,
+    /// How the failed job's log is shown, GitLab pipelines only link to their failed jobs
+    #[serde(default)]
+    pub logs: LogSettings,
+    pub threads: Option<ThreadSettings>,
+    #[serde(default)]
+    pub mentions: Vec<MentionSettings>,
+    /// Overrides for how each conclusion is shown, keyed by conclusion, e.g. `cancelled`
+    #[serde(default)]
+    pub conclusions: HashMap<RunConclusion, ConclusionSettings>,
+    /// Share one message between every configured workflow that runs for a commit. The shared
+    /// message only lists each run's status, without failure logs, diagnostics, failing streaks or
+    /// timing, and can't be combined with `threads` or `artifacts`. GitLab runs a single pipeline
+    /// per commit, so it's only used on GitHub
+    pub aggregate: Option<AggregateSettings>,
+    /// Attach files from the run's artifacts to the message of a successful build, only on GitHub
+    pub artifacts: Option<ArtifactSettings>,
+    /// Language of the bot's messages, e.g. `de`, takes precedence over the channel's locale
+    pub locale: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct ModVersionVariable {
     pub path: String,
     pub regex: String,
@@ -19,7 +43,195 @@ pub struct ModVersionVariable {
     pub format: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
+pub struct ChangelogSettings {
+    /// Commit types left out of the changelog, `other` hides non-conventional commits
+    #[serde(default)]
+    pub hidden_types: Vec<String>,
+    /// Overrides for section titles, keyed by commit type
+    #[serde(default)]
+    pub titles: HashMap<String, String>,
+    #[serde(default = "bool_true")]
+    pub authors: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ReleaseSettings {
+    #[serde(default = "default_release_actions")]
+    pub actions: Vec<ReleaseWebhookEventAction>,
+    /// Channel to announce releases in, defaults to the repository's build channel
+    pub channel_id: Option<ChannelId>,
+    #[serde(default = "bool_true")]
+    pub assets: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LogSettings {
+    /// Show an excerpt of the failed job's log in the embed
+    #[serde(default = "bool_true")]
+    pub excerpt: bool,
+    /// Attach the failed job's log to the message
+    #[serde(default = "bool_true")]
+    pub attach: bool,
+    /// Regex matching the first line of the error region, checked before the built-in patterns
+    pub error_pattern: Option<String>,
+    /// Number of lines shown from where the error region starts
+    #[serde(default = "default_log_context")]
+    pub context: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            excerpt: true,
+            attach: true,
+            error_pattern: None,
+            context: default_log_context(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ThreadSettings {
+    #[serde(default)]
+    pub mode: ThreadMode,
+    /// Minutes of inactivity before the thread is archived, one of 60, 1440, 4320 or 10080
+    #[serde(default = "default_auto_archive")]
+    pub auto_archive: AutoArchiveDuration,
+}
+
+#[derive(Deserialize, Clone, Default, PartialEq)]
+pub enum ThreadMode {
+    /// Open a thread on every build message
+    #[default]
+    #[serde(rename = "all")]
+    All,
+    /// Only open a thread once a build has failed
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MentionSettings {
+    /// Branches this applies to, all branches when empty
+    #[serde(default)]
+    pub branches: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<RoleId>,
+    #[serde(default)]
+    pub users: Vec<UserId>,
+    /// Follow up once the branch builds successfully again
+    #[serde(default = "bool_true")]
+    pub fixed: bool,
+}
+
+impl MentionSettings {
+    pub fn applies_to(&self, branch: &str) -> bool {
+        self.branches.is_empty() || self.branches.iter().any(|b| b == branch)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ConclusionSettings {
+    pub emoji: Option<String>,
+    pub label: Option<String>,
+    /// Embed color as a number, e.g. `0x95a5a6`
+    pub color: Option<u32>,
+    #[serde(default)]
+    pub delete: DeleteMode,
+}
+
+#[derive(Deserialize, Clone, Default, PartialEq)]
+pub enum DeleteMode {
+    /// Keep the message
+    #[default]
+    #[serde(rename = "never")]
+    Never,
+    /// Delete the message once a newer run of the workflow on the same branch has been announced
+    #[serde(rename = "superseded")]
+    Superseded,
+    /// Always delete the message
+    #[serde(rename = "always")]
+    Always,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AggregateSettings {
+    /// Workflows that have to succeed before buttons are shown, every configured workflow when
+    /// empty
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ArtifactSettings {
+    /// Artifacts the files are taken from, every artifact of the run when empty
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// Patterns a file's name has to match to be attached, where `*` matches anything
+    #[serde(default = "default_artifact_include")]
+    pub include: Vec<String>,
+    /// Patterns of files left out even when they're included, e.g. `*-sources.jar`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl RepoSettings {
+    /// Reads a `.ci-preview.yml`, rejecting options an aggregated message can't show
+    pub fn parse(contents: &str) -> Option<Self> {
+        let settings = serde_norway::from_str::<Self>(contents)
+            .inspect_err(|e| warn!("Failed to parse repository config: {e}"))
+            .ok()?;
+
+        if settings.aggregate.is_some()
+            && (settings.threads.is_some() || settings.artifacts.is_some())
+        {
+            warn!("Repository config combines `aggregate` with `threads` or `artifacts`");
+            return None;
+        }
+
+        Some(settings)
+    }
+
+    /// Workflows an aggregated message waits for before it's final
+    pub fn required_workflows(&self) -> &[String] {
+        match &self.aggregate {
+            Some(aggregate) if !aggregate.required.is_empty() => &aggregate.required,
+            _ => &self.workflows,
+        }
+    }
+}
+
+impl ChangelogSettings {
+    /// Whether commits of the type are left out, types are matched regardless of case
+    pub fn is_hidden(&self, r#type: &str) -> bool {
+        self.hidden_types
+            .iter()
+            .any(|hidden| hidden.eq_ignore_ascii_case(r#type))
+    }
+}
+
+impl ModVersionVariable {
+    /// Finds the mod version in the contents of the version file
+    pub fn capture(&self, regex: &Regex, contents: &str) -> Option<String> {
+        regex
+            .captures(contents)
+            .and_then(|c| c.get(self.group))
+            .map(|c| c.as_str().to_string())
+    }
+
+    /// The version shown in messages, the configured format with its variables filled in
+    pub fn display(&self, mod_version: &str, minecraft_version: &str, build_number: i64) -> String {
+        self.format
+            .clone()
+            .unwrap_or_else(|| mod_version.to_string())
+            .replace("${mod_version}", mod_version)
+            .replace("${minecraft_version}", minecraft_version)
+            .replace("${build_number}", &build_number.to_string())
+    }
+}
+
+#[derive(Deserialize, Clone)]
 pub struct Button {
     pub style: ButtonStyle,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,6 +245,19 @@ pub struct Button {
 }
 
 impl Button {
+    /// Fills in the placeholders of the button's url, e.g. `${version}`
+    pub fn resolve_url(&self, variables: &[(&str, &str)]) -> String {
+        let url = self.url.clone().unwrap_or_default();
+
+        variables.iter().fold(url, |url, (placeholder, value)| {
+            url.replace(placeholder, value)
+        })
+    }
+
+    pub fn is_link(&self) -> bool {
+        matches!(self.style, ButtonStyle::Link)
+    }
+
     pub fn convert(&self, id: &str, url: &str) -> CreateButton {
         let mut button: CreateButton = match &self.style {
             ButtonStyle::Link => CreateButton::new_link(url),
@@ -52,7 +277,7 @@ impl Button {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub enum ButtonStyle {
     #[serde(rename = "primary")]
     Primary,
@@ -65,3 +290,28 @@ pub enum ButtonStyle {
     #[serde(rename = "link")]
     Link,
 }
+
+fn bool_true() -> bool {
+    true
+}
+
+fn default_release_actions() -> Vec<ReleaseWebhookEventAction> {
+    // `published` is sent for pre-releases too, also listening for `prereleased` would announce
+    // them twice
+    vec![
+        ReleaseWebhookEventAction::Published,
+        ReleaseWebhookEventAction::Edited,
+    ]
+}
+
+fn default_log_context() -> usize {
+    20
+}
+
+fn default_artifact_include() -> Vec<String> {
+    vec!["*".into()]
+}
+
+fn default_auto_archive() -> AutoArchiveDuration {
+    AutoArchiveDuration::OneDay
+}
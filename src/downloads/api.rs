@@ -0,0 +1,139 @@
+use crate::app::App;
+use crate::downloads::token::DownloadToken;
+use crate::error::Error;
+use crate::error::*;
+use crate::github::artifacts::patterns;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use snafu::{OptionExt, ResultExt};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Largest artifact a single file is extracted from, and largest file extracted from it, the whole
+/// archive is streamed at any size
+const EXTRACT_LIMIT: u64 = 256 * 1024 * 1024;
+
+/// Serves an artifact, or a file inside it, through the bot's GitHub credentials
+pub async fn download(
+    State(app): State<App>,
+    Path(token): Path<String>,
+) -> Result<Response, Error> {
+    let token = DownloadToken::verify(&token).context(InvalidDownloadTokenSnafu)?;
+
+    if token.is_expired() {
+        return Err(Error::DownloadExpired);
+    }
+
+    let response = app
+        .github_get(format!(
+            "https://api.github.com/repos/{}/actions/artifacts/{}/zip",
+            token.repository, token.artifact_id
+        ))
+        .send()
+        .await
+        .context(ReqwestSnafu)?;
+
+    // Artifacts expire on GitHub's side too, usually long before our links do
+    if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Err(Error::ArtifactNotFound);
+    }
+
+    let mut response = response.error_for_status().context(ReqwestSnafu)?;
+
+    let Some(file) = token.file else {
+        let filename = format!("{}.zip", token.name);
+
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, content_disposition(&filename)),
+            ],
+            Body::from_stream(response.bytes_stream()),
+        )
+            .into_response());
+    };
+
+    // Files can only be found in a complete archive, so it has to be downloaded first
+    if response
+        .content_length()
+        .is_some_and(|length| length > EXTRACT_LIMIT)
+    {
+        return Err(Error::ArtifactTooLarge);
+    }
+
+    let mut archive = Vec::new();
+
+    // The length isn't always known up front, so the limit is checked as the archive comes in
+    while let Some(chunk) = response.chunk().await.context(ReqwestSnafu)? {
+        if (archive.len() + chunk.len()) as u64 > EXTRACT_LIMIT {
+            return Err(Error::ArtifactTooLarge);
+        }
+
+        archive.extend_from_slice(&chunk);
+    }
+
+    let (filename, data) = tokio::task::spawn_blocking(move || extract_file(&archive, &file))
+        .await
+        .context(TaskJoinSnafu)?
+        .context(ArtifactNotFoundSnafu)?;
+    let data = data.context(ArtifactTooLargeSnafu)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition(&filename)),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// First file in the archive whose name matches the pattern, along with its contents unless they
+/// turn out to be too large
+fn extract_file(archive: &[u8], pattern: &str) -> Option<(String, Option<Vec<u8>>)> {
+    let pattern = patterns(&[pattern.to_string()]).into_iter().next()?;
+    let mut archive = ZipArchive::new(Cursor::new(archive)).ok()?;
+
+    for index in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(index) else {
+            continue;
+        };
+
+        let Some(name) = file
+            .enclosed_name()
+            .and_then(|path| path.file_name()?.to_str().map(String::from))
+        else {
+            continue;
+        };
+
+        if file.is_dir() || !pattern.is_match(&name) {
+            continue;
+        }
+
+        // The size the archive claims can't be trusted, so decompressing stops just past the limit
+        let mut data = Vec::new();
+        file.by_ref()
+            .take(EXTRACT_LIMIT + 1)
+            .read_to_end(&mut data)
+            .ok()?;
+
+        if data.len() as u64 > EXTRACT_LIMIT {
+            return Some((name, None));
+        }
+
+        return Some((name, Some(data)));
+    }
+
+    None
+}
+
+pub fn content_disposition(filename: &str) -> String {
+    let filename = filename.replace(
+        |c: char| c == '"' || c == '\\' || !c.is_ascii() || c.is_control(),
+        "_",
+    );
+
+    format!("attachment; filename=\"{filename}\"")
+}
@@ -0,0 +1,179 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use hmac_sha256::HMAC;
+use serde::{Deserialize, Serialize};
+use std::env;
+use subtle::ConstantTimeEq;
+
+/// Days a download link keeps working, unless `DOWNLOAD_EXPIRY` says otherwise
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+
+/// What a signed download link gives access to, without needing a GitHub account
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DownloadToken {
+    /// Full name of the repository, e.g. `owner/repo`
+    pub repository: String,
+    pub artifact_id: u64,
+    /// Name of the artifact, used for the name of the downloaded archive
+    pub name: String,
+    /// Pattern of the file inside the artifact served instead of the whole archive, where `*`
+    /// matches anything
+    pub file: Option<String>,
+    /// Unix timestamp after which the link stops working
+    pub expires_at: i64,
+}
+
+impl DownloadToken {
+    pub fn new(repository: &str, artifact_id: u64, name: &str, file: Option<&str>) -> Self {
+        let expiry = env::var("DOWNLOAD_EXPIRY")
+            .ok()
+            .and_then(|days| days.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_EXPIRY_DAYS);
+
+        Self {
+            repository: repository.to_string(),
+            artifact_id,
+            name: name.to_string(),
+            file: file.map(String::from),
+            expires_at: Utc::now().timestamp() + expiry * 24 * 60 * 60,
+        }
+    }
+
+    /// Public link to the download, when both `PUBLIC_URL` and `DOWNLOAD_SECRET` are configured
+    pub fn url(&self) -> Option<String> {
+        let public_url = env::var("PUBLIC_URL").ok()?;
+        let token = self.encode(&secret()?)?;
+
+        Some(format!(
+            "{}/download/{token}",
+            public_url.trim_end_matches('/')
+        ))
+    }
+
+    /// Reads a token from a download link, as long as it was signed by us
+    pub fn verify(token: &str) -> Option<Self> {
+        Self::decode(token, &secret()?)
+    }
+
+    /// The token as `{payload}.{signature}`, signed with the secret
+    fn encode(&self, secret: &str) -> Option<String> {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).ok()?);
+        let signature = signature(&payload, secret);
+
+        Some(format!("{payload}.{signature}"))
+    }
+
+    fn decode(token: &str, secret: &str) -> Option<Self> {
+        let (payload, signature) = token.split_once('.')?;
+
+        if !signature_matches(payload, signature, secret) {
+            return None;
+        }
+
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now().timestamp()
+    }
+}
+
+/// Signature of the value with `DOWNLOAD_SECRET`, when it's configured
+pub fn sign(value: &str) -> Option<String> {
+    Some(signature(value, &secret()?))
+}
+
+/// Whether the signature was made for the value with `DOWNLOAD_SECRET`
+pub fn verify_signature(value: &str, signature: &str) -> bool {
+    secret().is_some_and(|secret| signature_matches(value, signature, &secret))
+}
+
+fn signature(value: &str, secret: &str) -> String {
+    URL_SAFE_NO_PAD.encode(HMAC::mac(value.as_bytes(), secret))
+}
+
+fn signature_matches(value: &str, signature: &str, secret: &str) -> bool {
+    let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+
+    HMAC::mac(value.as_bytes(), secret).ct_eq(&signature).into()
+}
+
+fn secret() -> Option<String> {
+    env::var("DOWNLOAD_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "download-secret";
+
+    fn token(expires_at: i64) -> DownloadToken {
+        DownloadToken {
+            repository: "owner/repo".into(),
+            artifact_id: 42,
+            name: "my-mod".into(),
+            file: Some("*.jar".into()),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn signed_token_round_trips() {
+        let encoded = token(i64::MAX).encode(SECRET).unwrap();
+        let decoded = DownloadToken::decode(&encoded, SECRET).unwrap();
+
+        assert_eq!(decoded.repository, "owner/repo");
+        assert_eq!(decoded.artifact_id, 42);
+        assert_eq!(decoded.name, "my-mod");
+        assert_eq!(decoded.file.as_deref(), Some("*.jar"));
+        assert!(!decoded.is_expired());
+    }
+
+    #[test]
+    fn expired_token_still_decodes_but_is_expired() {
+        let encoded = token(Utc::now().timestamp() - 1).encode(SECRET).unwrap();
+
+        assert!(
+            DownloadToken::decode(&encoded, SECRET)
+                .unwrap()
+                .is_expired()
+        );
+    }
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let encoded = token(i64::MAX).encode(SECRET).unwrap();
+        let (_, signature) = encoded.split_once('.').unwrap();
+
+        let mut other = token(i64::MAX);
+        other.artifact_id = 43;
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&other).unwrap());
+
+        assert!(DownloadToken::decode(&format!("{forged_payload}.{signature}"), SECRET).is_none());
+    }
+
+    #[test]
+    fn tampered_signature_is_rejected() {
+        let encoded = token(i64::MAX).encode(SECRET).unwrap();
+        let (payload, _) = encoded.split_once('.').unwrap();
+
+        let forged = signature(payload, "another-secret");
+
+        assert!(DownloadToken::decode(&format!("{payload}.{forged}"), SECRET).is_none());
+        assert!(DownloadToken::decode(&format!("{payload}.not-base64!"), SECRET).is_none());
+        assert!(DownloadToken::decode(payload, SECRET).is_none());
+    }
+
+    #[test]
+    fn token_signed_with_another_secret_is_rejected() {
+        let encoded = token(i64::MAX).encode("another-secret").unwrap();
+
+        assert!(DownloadToken::decode(&encoded, SECRET).is_none());
+    }
+}
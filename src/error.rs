@@ -2,6 +2,8 @@ use axum::extract::rejection::BytesRejection;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use hex::FromHexError;
+use rusqlite::ErrorCode;
+use serenity::http::HttpError;
 use snafu::Snafu;
 
 #[derive(Snafu, Debug)]
@@ -10,6 +12,46 @@ pub enum Error {
     // Deserialization
     #[snafu(display("Encountered error during json deserialization"))]
     DeserializationErrorJson { source: serde_json::Error },
+    #[snafu(display("Encountered error during json serialization"))]
+    SerializationErrorJson { source: serde_json::Error },
+
+    // History
+    #[snafu(display("Database error: {}", source))]
+    Database { source: rusqlite::Error },
+    #[snafu(display("Background task failed: {}", source))]
+    TaskJoin { source: tokio::task::JoinError },
+    #[snafu(display("Build not found"))]
+    BuildNotFound,
+    #[snafu(display("Repository not found"))]
+    RepositoryNotFound,
+
+    // Queue
+    #[snafu(display("Too many events are waiting to be processed"))]
+    QueueFull,
+    #[snafu(display("Unauthorized"))]
+    Unauthorized,
+
+    // Downloads
+    #[snafu(display("Download link is invalid"))]
+    InvalidDownloadToken,
+    #[snafu(display("Download link has expired"))]
+    DownloadExpired,
+    #[snafu(display("Artifact not found, it may have expired"))]
+    ArtifactNotFound,
+    #[snafu(display("Artifact is too large to extract a file from, download all of it instead"))]
+    ArtifactTooLarge,
+
+    // Mirror
+    #[snafu(display("Mirrored artifact not found"))]
+    MirrorNotFound,
+    #[snafu(display("Failed to access the mirror: {}", source))]
+    MirrorIo { source: std::io::Error },
+    #[snafu(display("Mirror storage responded with {}", status))]
+    MirrorRequest { status: reqwest::StatusCode },
+
+    // GitLab
+    #[snafu(display("X-Gitlab-Token header is missing or invalid"))]
+    InvalidGitlabToken,
 
     // Unsorted
     #[snafu(display("Invalid body"))]
@@ -36,16 +78,74 @@ pub enum Error {
     FailedToUnwrapValue,
     #[snafu(display("Invalid Regex"))]
     InvalidRegex { source: regex::Error },
-    #[snafu(display("Cannot find message"))]
-    CannotFindMessage,
     #[snafu(display("Failed to send http request: {}", source))]
     Reqwest { source: reqwest::Error },
+    #[snafu(display("GitHub request failed: {}", source))]
+    Github { source: octocrab::Error },
     #[snafu(display("Failed to find application emoji"))]
     FailedToFindEmoji { source: serenity::Error },
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+        let status = match self {
+            Error::BuildNotFound
+            | Error::RepositoryNotFound
+            | Error::InvalidDownloadToken
+            | Error::ArtifactNotFound
+            | Error::MirrorNotFound => StatusCode::NOT_FOUND,
+            Error::DownloadExpired => StatusCode::GONE,
+            Error::Database { .. }
+            | Error::TaskJoin { .. }
+            | Error::MirrorIo { .. }
+            | Error::MirrorRequest { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::QueueFull => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        (status, self.to_string()).into_response()
     }
 }
+
+impl Error {
+    /// Whether trying again later could succeed, as opposed to errors caused by the event itself
+    /// or by the repository's setup, which would only fail the same way again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Reqwest { source } => is_transient_request(source),
+            Error::Github { source } => match source {
+                octocrab::Error::GitHub { source, .. } => is_transient_status(source.status_code),
+                // No response came back, e.g. the connection failed or timed out
+                octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. } => true,
+                _ => false,
+            },
+            Error::FailedToSendMessage { source } | Error::FailedToFindEmoji { source } => {
+                match source {
+                    serenity::Error::Http(HttpError::Request(source)) => {
+                        is_transient_request(source)
+                    }
+                    serenity::Error::Http(error) => {
+                        error.status_code().is_some_and(is_transient_status)
+                    }
+                    _ => false,
+                }
+            }
+            Error::Database { source } => matches!(
+                source.sqlite_error_code(),
+                Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+            ),
+            Error::MirrorRequest { status } => is_transient_status(*status),
+            _ => false,
+        }
+    }
+}
+
+/// Requests that never got a response failed on the way there, which is worth another try
+fn is_transient_request(error: &reqwest::Error) -> bool {
+    error.status().is_none_or(is_transient_status)
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
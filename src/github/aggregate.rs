@@ -0,0 +1,312 @@
+use crate::app::App;
+use crate::builds::alerts::notify_transition;
+use crate::builds::conclusions::RunConclusion;
+use crate::builds::conclusions::{application_emoji, run_status};
+use crate::builds::messages::{BuildMessage, edit_or_send};
+use crate::config::app_config::MirrorSettings;
+use crate::config::repo_config::{ConclusionSettings, RepoSettings};
+use crate::error::Error;
+use crate::github::artifacts::artifact_sources;
+use crate::github::octocrab::models::workflows_extension::WorkflowRun;
+use crate::history::store::{Build, BuildLink, BuildStatus};
+use crate::i18n::Locale;
+use crate::mirror::archive::mirror_artifacts;
+use crate::util::time::format_duration;
+use octocrab::models::webhook_events::payload::WorkflowRunWebhookEventAction;
+use serenity::all::colours::branding;
+use serenity::all::{ChannelId, CreateActionRow, CreateEmbed, MessageId};
+use std::collections::HashMap;
+use tracing::log::error;
+
+/// Every configured workflow's run for one commit, sharing a single message
+#[derive(Default)]
+pub struct AggregateBuild {
+    message_id: Option<MessageId>,
+    /// Keyed by workflow path
+    runs: HashMap<String, AggregateRun>,
+}
+
+struct AggregateRun {
+    name: String,
+    url: String,
+    completed: bool,
+    conclusion: Option<RunConclusion>,
+    duration: Option<i64>,
+}
+
+pub struct AggregateEvent<'a> {
+    pub action: WorkflowRunWebhookEventAction,
+    pub workflow: &'a WorkflowRun,
+    pub build: Build,
+    pub channel_id: ChannelId,
+    /// Shown once the required workflows succeed, when the repository has buttons enabled
+    pub buttons: Option<CreateActionRow>,
+    pub links: Vec<BuildLink>,
+    pub locale: Locale,
+    pub mirror: Option<MirrorSettings>,
+}
+
+/// Updates the commit's shared message with the run's progress, creating it for the first run
+pub async fn handle_aggregated(
+    app: &App,
+    config: &RepoSettings,
+    event: AggregateEvent<'_>,
+    embed: impl FnOnce(&str, &str) -> CreateEmbed,
+) -> Result<(), Error> {
+    let workflow = event.workflow;
+    let locale = event.locale;
+    let completed = match event.action {
+        WorkflowRunWebhookEventAction::InProgress => false,
+        WorkflowRunWebhookEventAction::Completed => true,
+        _ => return Ok(()),
+    };
+
+    // Runs of the commit's workflows are handled one at a time, as they all edit the same message
+    let aggregate = app
+        .cache
+        .aggregates
+        .get_with(
+            (
+                event.build.repository.to_lowercase(),
+                event.build.head_sha.clone(),
+            ),
+            async { Default::default() },
+        )
+        .await;
+    let mut aggregate = aggregate.lock().await;
+
+    // The cache doesn't survive restarts, so pick up where the commit's recorded runs left off
+    if aggregate.message_id.is_none() {
+        *aggregate = recorded_aggregate(app, &event.build).await;
+    }
+
+    let started_at = if workflow.run_attempt > 1 {
+        workflow.run_started_at
+    } else {
+        workflow.inner.created_at
+    };
+    let duration = (workflow.inner.updated_at - started_at).num_seconds();
+
+    aggregate.runs.insert(
+        workflow.path.clone(),
+        AggregateRun {
+            name: workflow.inner.name.clone(),
+            url: workflow.inner.html_url.to_string(),
+            completed,
+            conclusion: workflow.conclusion_enum.filter(|_| completed),
+            duration: completed.then_some(duration),
+        },
+    );
+
+    let required = config.required_workflows();
+    let finished = aggregate.runs.values().all(|run| run.completed)
+        && required
+            .iter()
+            .all(|path| aggregate.runs.get(path).is_some_and(|run| run.completed));
+    let succeeded = required.iter().all(|path| {
+        aggregate
+            .runs
+            .get(path)
+            .is_some_and(|run| run.conclusion == Some(RunConclusion::Success))
+    });
+
+    let processing = application_emoji(app, app.emojis.processing).await?;
+    let mut rows = String::new();
+
+    for path in &config.workflows {
+        match aggregate.runs.get(path) {
+            Some(run) if run.completed => {
+                let (status, _) = run_status(
+                    app,
+                    locale,
+                    run.conclusion,
+                    conclusion_settings(config, run),
+                )
+                .await?;
+                let duration = run
+                    .duration
+                    .filter(|duration| *duration > 0)
+                    .map(|duration| {
+                        let duration = format_duration(locale, duration);
+                        locale.format("build.in", &[("duration", &duration)])
+                    })
+                    .unwrap_or_default();
+
+                rows += &format!("{status} [{}]({}){duration}\n", run.name, run.url);
+            }
+            Some(run) => {
+                rows += &format!(
+                    "{processing} [{}]({}) {}\n",
+                    run.name,
+                    run.url,
+                    locale.text("aggregate.running")
+                )
+            }
+            None if required.contains(path) => {
+                rows += &format!("⏳ `{path}` {}\n", locale.text("aggregate.waiting"))
+            }
+            None => {}
+        }
+    }
+
+    let (status, color) = if finished {
+        // The first required workflow that didn't succeed decides how the commit is shown
+        let conclusion = required
+            .iter()
+            .filter_map(|path| aggregate.runs.get(path))
+            .find(|run| run.conclusion != Some(RunConclusion::Success))
+            .map(|run| run.conclusion)
+            .unwrap_or(Some(RunConclusion::Success));
+        let settings = conclusion.and_then(|conclusion| config.conclusions.get(&conclusion));
+        let (status, color) = run_status(app, locale, conclusion, settings).await?;
+
+        (format!("**{status}**"), color)
+    } else {
+        let done = aggregate.runs.values().filter(|run| run.completed).count();
+        let total = aggregate.runs.len().max(required.len());
+
+        (
+            format!(
+                "{} {processing}",
+                locale.format("aggregate.progress", &[("done", &done), ("total", &total)])
+            ),
+            branding::BLURPLE,
+        )
+    };
+
+    let embed = embed(&status, &rows).color(color);
+    let components = event
+        .buttons
+        .filter(|_| finished && succeeded)
+        .map(|buttons| vec![buttons])
+        .unwrap_or_default();
+
+    let (message_id, _) = edit_or_send(
+        app,
+        event.channel_id,
+        aggregate.message_id,
+        BuildMessage {
+            embed,
+            components,
+            attachments: Vec::new(),
+        },
+    )
+    .await?;
+    aggregate.message_id = Some(message_id);
+
+    if completed
+        && workflow.conclusion_enum == Some(RunConclusion::Success)
+        && let Some(mirror) = &event.mirror
+    {
+        mirror_artifacts(
+            app,
+            mirror,
+            &event.build,
+            artifact_sources(app, workflow).await,
+        )
+        .await;
+    }
+
+    let build = Build {
+        message_link: Some(app.message_link(event.channel_id, message_id).await),
+        message_id: Some(message_id.get()),
+        ..event.build
+    };
+
+    let build = if completed {
+        Build {
+            status: BuildStatus::Completed,
+            conclusion: workflow
+                .conclusion_enum
+                .and_then(|c| serde_json::to_value(c).ok())
+                .and_then(|c| c.as_str().map(String::from)),
+            completed_at: Some(workflow.inner.updated_at),
+            duration: Some(duration),
+            links: if workflow.conclusion_enum == Some(RunConclusion::Success) {
+                event.links
+            } else {
+                Vec::new()
+            },
+            ..build
+        }
+    } else {
+        build
+    };
+
+    if completed && !config.mentions.is_empty() {
+        let previous = app
+            .history
+            .previous_build(
+                &build.repository,
+                &build.branch,
+                &build.workflow,
+                build.run_id,
+            )
+            .await
+            .inspect_err(|e| error!("Failed to find previous build: {e}"))
+            .ok()
+            .flatten();
+
+        notify_transition(
+            app,
+            locale,
+            &config.mentions,
+            event.channel_id,
+            message_id,
+            &build,
+            previous.as_ref(),
+        )
+        .await;
+    }
+
+    if let Err(e) = app.history.record(build).await {
+        error!("Failed to record build: {e}");
+    }
+
+    Ok(())
+}
+
+/// Rebuilds a commit's shared message state from the runs recorded for it
+async fn recorded_aggregate(app: &App, build: &Build) -> AggregateBuild {
+    let builds = app
+        .history
+        .commit_builds(&build.repository, &build.head_sha)
+        .await
+        .inspect_err(|e| error!("Failed to find the commit's builds: {e}"))
+        .unwrap_or_default();
+
+    let message_id = builds
+        .iter()
+        .find_map(|build| build.message_id)
+        .map(MessageId::new);
+    let runs = builds
+        .into_iter()
+        .map(|build| {
+            let completed = build.status == BuildStatus::Completed;
+            let conclusion = build
+                .conclusion
+                .and_then(|conclusion| serde_json::from_value(conclusion.into()).ok());
+
+            // The workflow's name isn't recorded, its next event fills it back in
+            let run = AggregateRun {
+                name: build.workflow.clone(),
+                url: build.url,
+                completed,
+                conclusion: conclusion.filter(|_| completed),
+                duration: build.duration.filter(|_| completed),
+            };
+
+            (build.workflow, run)
+        })
+        .collect();
+
+    AggregateBuild { message_id, runs }
+}
+
+fn conclusion_settings<'a>(
+    config: &'a RepoSettings,
+    run: &AggregateRun,
+) -> Option<&'a ConclusionSettings> {
+    run.conclusion
+        .and_then(|conclusion| config.conclusions.get(&conclusion))
+}
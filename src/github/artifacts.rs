@@ -0,0 +1,328 @@
+use crate::app::App;
+use crate::config::repo_config::{ArtifactSettings, RepoSettings};
+use crate::downloads::token::DownloadToken;
+use crate::github::octocrab::models::workflows_extension::{ArtifactsList, WorkflowRun};
+use crate::i18n::Locale;
+use crate::mirror::archive::ArtifactSource;
+use crate::util::text::truncated_list;
+use octocrab::models::workflows::WorkflowListArtifact;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serenity::all::CreateAttachment;
+use std::env;
+use std::io::{Cursor, Read};
+use tracing::log::error;
+use zip::ZipArchive;
+
+/// `${artifact:name}` in a button's url, or `${artifact:name/pattern}` for a file inside it
+static DOWNLOAD_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$\{artifact:([^}/]+)(?:/([^}]+))?\}").unwrap());
+
+/// Total size of the files attached to one message, below Discord's 10 MiB for servers without
+/// boosts as the embed and form data count towards that too
+static UPLOAD_LIMIT: Lazy<u64> = Lazy::new(|| {
+    env::var("UPLOAD_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse::<u64>().ok())
+        .unwrap_or(8 * 1024 * 1024)
+});
+
+/// Largest artifact downloaded to look for files in, bigger ones are only linked
+const DOWNLOAD_LIMIT: usize = 64 * 1024 * 1024;
+/// Most artifacts GitHub lists per page
+const ARTIFACTS_PER_PAGE: usize = 100;
+/// Discord's limit for attachments on a message
+const ATTACHMENTS_PER_MESSAGE: usize = 10;
+/// Longest the list of artifact links gets before the rest are summarized
+const FIELD_LIMIT: usize = 512;
+
+/// Files picked from a run's artifacts, split by whether they fit on the message
+#[derive(Default)]
+pub struct BuildArtifacts {
+    pub attachments: Vec<CreateAttachment>,
+    /// Files that didn't fit, as their name, size and where they can be downloaded
+    pub links: Vec<(String, u64, String)>,
+}
+
+/// Downloads the repository's selected artifacts of a run and picks the files to attach
+pub async fn build_artifacts(
+    app: &App,
+    workflow: &WorkflowRun,
+    repository: &str,
+    settings: &ArtifactSettings,
+) -> BuildArtifacts {
+    let mut artifacts = BuildArtifacts::default();
+
+    let include = patterns(&settings.include);
+    let exclude = patterns(&settings.exclude);
+    let mut uploaded = 0;
+
+    for artifact in list_artifacts(app, workflow).await {
+        if artifact.expired
+            || !settings.names.is_empty() && !settings.names.contains(&artifact.name)
+        {
+            continue;
+        }
+
+        if artifact.size_in_bytes > DOWNLOAD_LIMIT {
+            let url = download_url(workflow, repository, &artifact, None);
+            artifacts
+                .links
+                .push((artifact.name.clone(), artifact.size_in_bytes as u64, url));
+            continue;
+        }
+
+        let Some(archive) = download_artifact(app, &artifact).await else {
+            continue;
+        };
+
+        let (include, exclude) = (include.clone(), exclude.clone());
+        let files = tokio::task::spawn_blocking(move || {
+            extract_files(archive, |name| {
+                include.iter().any(|pattern| pattern.is_match(name))
+                    && !exclude.iter().any(|pattern| pattern.is_match(name))
+            })
+        })
+        .await
+        .inspect_err(|e| error!("Failed to extract artifact {}: {e}", artifact.name))
+        .unwrap_or_default();
+
+        for (name, size, data) in files {
+            match data {
+                Some(data)
+                    if uploaded + size <= *UPLOAD_LIMIT
+                        && artifacts.attachments.len() < ATTACHMENTS_PER_MESSAGE =>
+                {
+                    uploaded += size;
+                    artifacts
+                        .attachments
+                        .push(CreateAttachment::bytes(data, name));
+                }
+                _ => {
+                    let url = download_url(workflow, repository, &artifact, Some(&name));
+                    artifacts.links.push((name, size, url));
+                }
+            }
+        }
+    }
+
+    artifacts
+}
+
+/// Download links for the artifact placeholders in the repository's button urls, signed when the
+/// bot is set up to proxy downloads and otherwise pointing at the artifact on GitHub
+pub async fn download_variables(
+    app: &App,
+    workflow: &WorkflowRun,
+    repository: &str,
+    config: &RepoSettings,
+) -> Vec<(String, String)> {
+    let placeholders = config
+        .buttons
+        .values()
+        .filter_map(|button| button.url.as_deref())
+        .flat_map(|url| DOWNLOAD_PLACEHOLDER.captures_iter(url))
+        .map(|captures| {
+            (
+                captures[0].to_string(),
+                captures[1].to_string(),
+                captures.get(2).map(|file| file.as_str().to_string()),
+            )
+        })
+        .collect::<Vec<(String, String, Option<String>)>>();
+
+    if placeholders.is_empty() {
+        return Vec::new();
+    }
+
+    let artifacts = list_artifacts(app, workflow).await;
+
+    placeholders
+        .into_iter()
+        .map(|(placeholder, name, file)| {
+            let artifact = artifacts
+                .iter()
+                .find(|artifact| artifact.name == name && !artifact.expired);
+
+            let url = match artifact {
+                Some(artifact) => download_url(workflow, repository, artifact, file.as_deref()),
+                // Link buttons need a valid url, so fall back to the run
+                None => workflow.inner.html_url.to_string(),
+            };
+
+            (placeholder, url)
+        })
+        .collect()
+}
+
+/// Signed link to the artifact or a file in it, or the artifact's page on GitHub when the bot
+/// isn't set up to proxy downloads
+fn download_url(
+    workflow: &WorkflowRun,
+    repository: &str,
+    artifact: &WorkflowListArtifact,
+    file: Option<&str>,
+) -> String {
+    DownloadToken::new(repository, *artifact.id, &artifact.name, file)
+        .url()
+        .unwrap_or_else(|| format!("{}/artifacts/{}", workflow.inner.html_url, artifact.id))
+}
+
+/// Embed field linking the files that were too big to attach
+pub fn artifact_links(locale: Locale, links: &[(String, u64, String)]) -> Option<(String, String)> {
+    if links.is_empty() {
+        return None;
+    }
+
+    let lines = links
+        .iter()
+        .map(|(name, size, url)| format!("[{name}]({url}) ({})", format_size(*size)))
+        .collect::<Vec<String>>();
+
+    Some((
+        locale.text("artifacts.title"),
+        truncated_list(&lines, FIELD_LIMIT, locale),
+    ))
+}
+
+/// Every artifact of the run, going through as many pages as GitHub splits them into
+pub async fn list_artifacts(app: &App, workflow: &WorkflowRun) -> Vec<WorkflowListArtifact> {
+    let mut artifacts = Vec::new();
+
+    for page in 1.. {
+        let response = async {
+            app.github_get(workflow.inner.artifacts_url.as_str())
+                .query(&[("per_page", ARTIFACTS_PER_PAGE), ("page", page)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<ArtifactsList>()
+                .await
+        };
+
+        match response.await {
+            Ok(list) => {
+                let last = list.artifacts.len() < ARTIFACTS_PER_PAGE;
+                artifacts.extend(list.artifacts);
+
+                if last {
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("Failed to list artifacts: {e}");
+                break;
+            }
+        }
+    }
+
+    artifacts
+}
+
+/// The run's artifacts that haven't expired, for the mirror to copy
+pub async fn artifact_sources(app: &App, workflow: &WorkflowRun) -> Vec<ArtifactSource> {
+    list_artifacts(app, workflow)
+        .await
+        .into_iter()
+        .filter(|artifact| !artifact.expired)
+        .map(|artifact| ArtifactSource {
+            download: app.github_get(artifact.archive_download_url.as_str()),
+            name: artifact.name,
+        })
+        .collect()
+}
+
+pub async fn download_artifact(app: &App, artifact: &WorkflowListArtifact) -> Option<Vec<u8>> {
+    let response = async {
+        app.github_get(artifact.archive_download_url.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    };
+
+    response
+        .await
+        .inspect_err(|e| error!("Failed to download artifact {}: {e}", artifact.name))
+        .ok()
+        .map(Vec::from)
+}
+
+/// Files of the archive whose name is wanted, with their size and their contents unless they're
+/// too big to ever be attached
+fn extract_files(
+    archive: Vec<u8>,
+    wanted: impl Fn(&str) -> bool,
+) -> Vec<(String, u64, Option<Vec<u8>>)> {
+    let mut archive = match ZipArchive::new(Cursor::new(archive)) {
+        Ok(archive) => archive,
+        Err(e) => {
+            error!("Failed to open artifact archive: {e}");
+            return Vec::new();
+        }
+    };
+
+    let mut files = Vec::new();
+
+    for index in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(index) else {
+            continue;
+        };
+
+        // Attachments have no folders, so only the file name is matched and kept
+        let Some(name) = file
+            .enclosed_name()
+            .and_then(|path| path.file_name()?.to_str().map(String::from))
+        else {
+            continue;
+        };
+
+        if file.is_dir() || !wanted(&name) {
+            continue;
+        }
+
+        // An entry can claim any size, reading one byte past the limit is enough to tell it's too
+        // large to attach
+        let mut data = Vec::new();
+
+        if let Err(e) = file.by_ref().take(*UPLOAD_LIMIT + 1).read_to_end(&mut data) {
+            error!("Failed to extract {name}: {e}");
+            continue;
+        }
+
+        let size = data.len() as u64;
+
+        if size > *UPLOAD_LIMIT {
+            files.push((name, size.max(file.size()), None));
+        } else {
+            files.push((name, size, Some(data)));
+        }
+    }
+
+    files
+}
+
+/// Turns patterns like `*-sources.jar` into regexes matching the whole file name
+pub fn patterns(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| {
+            let pattern = regex::escape(pattern)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".");
+
+            Regex::new(&format!("^{pattern}$"))
+                .inspect_err(|e| error!("Invalid artifact pattern: {e}"))
+                .ok()
+        })
+        .collect()
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1024 * 1024 => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        bytes if bytes >= 1024 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        bytes => format!("{bytes} B"),
+    }
+}
@@ -0,0 +1,58 @@
+use crate::app::App;
+use crate::builds::conclusions::RunConclusion;
+use crate::builds::conclusions::conclusion_label;
+use crate::history::store::Build;
+use crate::i18n::Locale;
+use crate::util::time::format_duration;
+use tracing::log::error;
+
+/// Earlier attempts of the build's workflow run, oldest first
+pub async fn previous_attempts(app: &App, build: &Build) -> Vec<Build> {
+    app.history
+        .attempts(&build.repository, build.run_id)
+        .await
+        .inspect_err(|e| error!("Failed to find previous attempts: {e}"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|attempt| attempt.run_attempt < build.run_attempt)
+        .collect()
+}
+
+/// One line per earlier attempt with its conclusion and duration, linking to that attempt's logs
+pub fn format_attempts(locale: Locale, attempts: &[Build]) -> String {
+    attempts
+        .iter()
+        .map(|attempt| {
+            let conclusion = attempt
+                .conclusion
+                .as_deref()
+                .and_then(|conclusion| serde_json::from_value(conclusion.into()).ok());
+
+            let icon = match conclusion {
+                Some(RunConclusion::Success) => "✅",
+                Some(RunConclusion::Failure | RunConclusion::TimedOut) => "❌",
+                Some(RunConclusion::Cancelled) => "🚫",
+                _ => "⚪",
+            };
+
+            let duration = attempt
+                .duration
+                .filter(|duration| *duration > 0)
+                .map(|duration| {
+                    locale.format(
+                        "build.in",
+                        &[("duration", &format_duration(locale, duration))],
+                    )
+                })
+                .unwrap_or_default();
+
+            format!(
+                "-# {icon} [{}]({}/attempts/{}) {}{duration}\n",
+                locale.format("attempt.label", &[("attempt", &attempt.run_attempt)]),
+                attempt.url,
+                attempt.run_attempt,
+                conclusion_label(locale, conclusion.as_ref())
+            )
+        })
+        .collect()
+}
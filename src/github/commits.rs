@@ -0,0 +1,129 @@
+use crate::app::App;
+use crate::builds::commits::{CommitInfo, CommitUser};
+use crate::github::octocrab::models::workflows_extension::WorkflowRun;
+use crate::history::store::Build;
+use octocrab::models::Repository;
+use octocrab::models::commits::{Commit, GitUser};
+use octocrab::models::webhook_events::payload::PushWebhookEventCommit;
+use tracing::log::error;
+
+impl From<PushWebhookEventCommit> for CommitInfo {
+    fn from(commit: PushWebhookEventCommit) -> Self {
+        Self {
+            id: commit.id,
+            url: commit.url.to_string(),
+            message: commit.message,
+            author: CommitUser::new(
+                Some(commit.author.user.name),
+                Some(commit.author.user.email),
+                commit.author.username,
+            ),
+        }
+    }
+}
+
+impl From<Commit> for CommitInfo {
+    fn from(commit: Commit) -> Self {
+        let (name, email) = commit
+            .commit
+            .author
+            .map(|author: GitUser| (author.name, author.email))
+            .unwrap_or_default();
+
+        Self {
+            id: commit.sha,
+            url: commit.html_url,
+            message: commit.commit.message,
+            author: CommitUser::new(name, email, commit.author.map(|author| author.login)),
+        }
+    }
+}
+
+/// Resolves the commits that went into a workflow run.
+///
+/// Commits recorded with the run by an earlier event are reused, then commits recorded from push
+/// events are preferred. Otherwise the compare API is asked for everything between the previously
+/// announced build on the branch, or the last one in the history after a restart, and the run's
+/// head. If neither is available the run's head commit is used on its own.
+pub async fn resolve_commits(
+    app: &App,
+    repo: &Repository,
+    branch: &str,
+    workflow: &WorkflowRun,
+    recorded: Option<&Build>,
+) -> Vec<CommitInfo> {
+    if let Some(recorded) = recorded.filter(|recorded| !recorded.commits.is_empty()) {
+        return recorded.commits.clone();
+    }
+
+    let head_sha = &workflow.inner.head_sha;
+
+    if let Some(commits) = app
+        .cache
+        .commits
+        .get(head_sha)
+        .await
+        .filter(|commits| !commits.is_empty())
+    {
+        return commits;
+    }
+
+    let previous_sha = match app
+        .cache
+        .announced_builds
+        .get(&(repo.id, branch.to_string()))
+        .await
+    {
+        Some(sha) => Some(sha),
+        None => match &repo.full_name {
+            Some(repository) => app
+                .history
+                .last_branch_build(repository, branch, *workflow.inner.id)
+                .await
+                .inspect_err(|e| error!("Failed to look up the branch's last build: {e}"))
+                .ok()
+                .flatten()
+                .map(|build| build.head_sha),
+            None => None,
+        },
+    }
+    .filter(|sha| sha != head_sha);
+
+    let mut commits = match (previous_sha, &repo.owner) {
+        (Some(previous_sha), Some(owner)) => octocrab::instance()
+            .commits(&owner.login, &repo.name)
+            .compare(previous_sha, head_sha)
+            .send()
+            .await
+            .map(|comparison| comparison.commits.into_iter().map(Into::into).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    if commits.is_empty() {
+        let head_commit = &workflow.inner.head_commit;
+        let html_url = repo
+            .html_url
+            .as_ref()
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+
+        commits.push(CommitInfo {
+            id: head_commit.id.clone(),
+            url: format!("{html_url}/commit/{}", head_commit.id),
+            message: head_commit.message.clone(),
+            author: CommitUser::new(
+                Some(head_commit.author.name.clone()),
+                Some(head_commit.author.email.clone()),
+                None,
+            ),
+        });
+    }
+
+    app.cache
+        .commits
+        .insert(head_sha.clone(), commits.clone())
+        .await;
+
+    commits
+}
@@ -0,0 +1,146 @@
+use crate::app::App;
+use crate::error::Error;
+use crate::error::*;
+use crate::github::octocrab::models::workflows_extension::{Annotation, JobsList};
+use crate::i18n::Locale;
+use crate::util::text::truncated_list;
+use crate::util::time::format_duration;
+use octocrab::models::workflows::{Conclusion, Job, Step};
+use snafu::ResultExt;
+
+/// Space in the embed description set aside for failure diagnostics
+const DIAGNOSTICS_LIMIT: usize = 800;
+/// Most jobs GitHub lists per page
+const JOBS_PER_PAGE: usize = 100;
+const ANNOTATIONS_PER_JOB: usize = 3;
+const ANNOTATION_MESSAGE_LIMIT: usize = 150;
+
+/// Every job of the run, going through as many pages as GitHub splits them into
+pub async fn fetch_jobs(app: &App, jobs_url: &str) -> Result<Vec<Job>, Error> {
+    let mut jobs = Vec::new();
+
+    for page in 1.. {
+        let jobs_list = app
+            .github_get(jobs_url)
+            .query(&[("per_page", JOBS_PER_PAGE), ("page", page)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(ReqwestSnafu)?
+            .json::<JobsList>()
+            .await
+            .context(ReqwestSnafu)?;
+
+        let last = jobs_list.jobs.len() < JOBS_PER_PAGE;
+        jobs.extend(jobs_list.jobs);
+
+        if last {
+            break;
+        }
+    }
+
+    Ok(jobs)
+}
+
+/// Lists the failed jobs of a run with their failed steps and error annotations, linking
+/// straight to the log of each failed step.
+pub async fn failure_diagnostics(app: &App, locale: Locale, jobs: &[Job]) -> Option<String> {
+    let mut lines = Vec::new();
+
+    for job in jobs.iter().filter(|job| is_failure(&job.conclusion)) {
+        let duration = job
+            .completed_at
+            .map(|completed_at| {
+                locale.format(
+                    "build.in",
+                    &[("duration", &duration(locale, job.started_at, completed_at))],
+                )
+            })
+            .unwrap_or_default();
+
+        lines.push(format!("❌ [{}]({}){}", job.name, job.html_url, duration));
+
+        for step in job.steps.iter().filter(|step| is_failure(&step.conclusion)) {
+            lines.push(format!(
+                "-# ↳ [{}]({}#step:{}:1){}",
+                step.name,
+                job.html_url,
+                step.number,
+                step_duration(locale, step)
+            ));
+        }
+
+        for annotation in annotations(app, job).await {
+            let message = annotation
+                .message
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(ANNOTATION_MESSAGE_LIMIT)
+                .collect::<String>();
+
+            lines.push(format!(
+                "-# `{}:{}` {}",
+                annotation.path, annotation.start_line, message
+            ));
+        }
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    Some(truncated_list(&lines, DIAGNOSTICS_LIMIT, locale))
+}
+
+async fn annotations(app: &App, job: &Job) -> Vec<Annotation> {
+    let Ok(response) = app
+        .github_get(format!("{}/annotations", job.check_run_url))
+        .send()
+        .await
+    else {
+        return Vec::new();
+    };
+
+    response
+        .json::<Vec<Annotation>>()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|annotation| annotation.annotation_level == "failure")
+        // Skip the generic "Process completed with exit code 1" annotation
+        .filter(|annotation| annotation.path != ".github")
+        .take(ANNOTATIONS_PER_JOB)
+        .collect()
+}
+
+pub fn is_failure(conclusion: &Option<Conclusion>) -> bool {
+    matches!(
+        conclusion,
+        Some(Conclusion::Failure) | Some(Conclusion::TimedOut)
+    )
+}
+
+fn step_duration(locale: Locale, step: &Step) -> String {
+    match (step.started_at, step.completed_at) {
+        (Some(started_at), Some(completed_at)) => {
+            format!(" ({})", duration(locale, started_at, completed_at))
+        }
+        _ => String::new(),
+    }
+}
+
+fn duration(
+    locale: Locale,
+    started_at: chrono::DateTime<chrono::Utc>,
+    completed_at: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let seconds = (completed_at - started_at).num_seconds();
+
+    if seconds > 0 {
+        format_duration(locale, seconds)
+    } else {
+        locale.plural("duration.seconds", 0, &[])
+    }
+}
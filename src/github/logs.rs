@@ -0,0 +1,272 @@
+use crate::app::App;
+use crate::config::repo_config::LogSettings;
+use crate::github::diagnostics::is_failure;
+use octocrab::models::workflows::Job;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serenity::all::CreateAttachment;
+use tracing::log::error;
+
+static TIMESTAMP: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?Z ?").unwrap());
+static ANSI_ESCAPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap());
+static COMPILER_ERROR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^e: |(^|\s)error(\[E\d+\])?:|\S+\.(java|kt|scala|groovy):\d+(:\d+)?: error")
+        .unwrap()
+});
+static STACK_TRACE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(Exception in thread .*|Caused by: .*|([\w$]+\.)+[\w$]*(Exception|Error)(: .*)?)$",
+    )
+    .unwrap()
+});
+
+const GRADLE_START: &str = "* What went wrong:";
+const GRADLE_END: &str = "* Try:";
+
+/// Lines shown from the tail of the log when no error region could be found
+const TAIL_LINES: usize = 15;
+/// Discord's limit for an embed field's value, minus room for the code block
+const EXCERPT_LIMIT: usize = 1000;
+/// Largest log attached to a message, anything above keeps only the end of the log
+const ATTACHMENT_LIMIT: usize = 8 * 1024 * 1024;
+
+pub struct FailedJobLog {
+    pub job_name: String,
+    pub excerpt: Option<String>,
+    pub attachment: Option<CreateAttachment>,
+}
+
+/// Fetches the log of the first failed job and prepares what the repository wants shown of it
+pub async fn failed_job_log(
+    app: &App,
+    jobs: &[Job],
+    settings: &LogSettings,
+) -> Option<FailedJobLog> {
+    if !settings.excerpt && !settings.attach {
+        return None;
+    }
+
+    let job = jobs.iter().find(|job| is_failure(&job.conclusion))?;
+    let log = fetch_log(app, job).await?;
+
+    let excerpt = settings.excerpt.then(|| {
+        let pattern = settings.error_pattern.as_deref().and_then(|pattern| {
+            Regex::new(pattern)
+                .inspect_err(|e| error!("Invalid log error pattern: {e}"))
+                .ok()
+        });
+
+        extract_excerpt(&log, pattern.as_ref(), settings.context)
+    });
+
+    let attachment = settings.attach.then(|| {
+        CreateAttachment::bytes(
+            trim_for_attachment(&log).as_bytes(),
+            format!("{}.log", job.name.replace(['/', ' '], "-")),
+        )
+    });
+
+    Some(FailedJobLog {
+        job_name: job.name.clone(),
+        excerpt: excerpt.filter(|excerpt| !excerpt.is_empty()),
+        attachment,
+    })
+}
+
+/// Downloads the log of a job, stripped of timestamps and ANSI escape codes
+async fn fetch_log(app: &App, job: &Job) -> Option<String> {
+    let response = app
+        .github_get(format!("{}/logs", job.url))
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+
+    let log = response.text().await.ok()?;
+
+    Some(
+        log.lines()
+            .map(|line| {
+                let line = TIMESTAMP.replace(line, "");
+                ANSI_ESCAPE.replace_all(&line, "").into_owned()
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    )
+}
+
+/// Finds the part of a log that's most likely to explain why the job failed
+fn extract_excerpt(log: &str, pattern: Option<&Regex>, context: usize) -> String {
+    let lines = log
+        .lines()
+        // Workflow commands like `##[group]` are noise in an excerpt
+        .filter(|line| !line.starts_with("##[") && !line.starts_with("[command]"))
+        .collect::<Vec<&str>>();
+
+    let region = pattern
+        .and_then(|pattern| find_line(&lines, |line| pattern.is_match(line)))
+        .map(|start| &lines[start..(start + context).min(lines.len())])
+        .or_else(|| gradle_region(&lines))
+        .or_else(|| {
+            find_line(&lines, |line| COMPILER_ERROR.is_match(line))
+                .map(|start| &lines[start..(start + context).min(lines.len())])
+        })
+        .or_else(|| stack_trace_region(&lines, context))
+        .unwrap_or_else(|| &lines[lines.len().saturating_sub(TAIL_LINES)..]);
+
+    let mut excerpt = String::new();
+
+    for line in region {
+        if excerpt.len() + line.len() + 1 > EXCERPT_LIMIT {
+            break;
+        }
+
+        excerpt += line;
+        excerpt += "\n";
+    }
+
+    // Stop the log from closing the code block it's displayed in
+    excerpt.trim_end().replace("```", "`\u{200b}``")
+}
+
+/// Keeps the end of the log when it's too large to attach
+fn trim_for_attachment(log: &str) -> &str {
+    if log.len() <= ATTACHMENT_LIMIT {
+        return log;
+    }
+
+    let mut start = log.len() - ATTACHMENT_LIMIT;
+    while !log.is_char_boundary(start) {
+        start += 1;
+    }
+
+    &log[start..]
+}
+
+fn find_line(lines: &[&str], predicate: impl Fn(&str) -> bool) -> Option<usize> {
+    lines.iter().position(|line| predicate(line))
+}
+
+fn gradle_region<'a>(lines: &'a [&'a str]) -> Option<&'a [&'a str]> {
+    let start = find_line(lines, |line| line.trim() == GRADLE_START)?;
+    let end = lines[start..]
+        .iter()
+        .position(|line| line.trim() == GRADLE_END)
+        .map(|end| start + end)
+        .unwrap_or(lines.len());
+
+    Some(&lines[start..end])
+}
+
+fn stack_trace_region<'a>(lines: &'a [&'a str], context: usize) -> Option<&'a [&'a str]> {
+    let start = lines.iter().enumerate().position(|(index, line)| {
+        STACK_TRACE.is_match(line.trim())
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.trim_start().starts_with("at "))
+    })?;
+
+    Some(&lines[start..(start + context).min(lines.len())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(lines: &[&str]) -> String {
+        lines.join("\n")
+    }
+
+    #[test]
+    fn starts_at_the_configured_pattern() {
+        let log = log(&[
+            "Compiling",
+            "FATAL: out of cheese",
+            "at line 1",
+            "at line 2",
+            "done",
+        ]);
+        let pattern = Regex::new("^FATAL").unwrap();
+
+        assert_eq!(
+            extract_excerpt(&log, Some(&pattern), 2),
+            "FATAL: out of cheese\nat line 1"
+        );
+    }
+
+    #[test]
+    fn prefers_gradles_failure_summary() {
+        let log = log(&[
+            "> Task :compileJava FAILED",
+            "error: cannot find symbol",
+            "* What went wrong:",
+            "Execution failed for task ':compileJava'.",
+            "* Try:",
+            "> Run with --stacktrace option.",
+        ]);
+
+        assert_eq!(
+            extract_excerpt(&log, None, 10),
+            "* What went wrong:\nExecution failed for task ':compileJava'."
+        );
+    }
+
+    #[test]
+    fn falls_back_to_compiler_errors() {
+        let log = log(&[
+            "> Task :compileKotlin",
+            "e: src/main/kotlin/Mod.kt:12:5 Unresolved reference: foo",
+            "warning: unused variable",
+            "> Task :compileKotlin FAILED",
+        ]);
+
+        assert_eq!(
+            extract_excerpt(&log, None, 2),
+            "e: src/main/kotlin/Mod.kt:12:5 Unresolved reference: foo\nwarning: unused variable"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_stack_traces() {
+        let log = log(&[
+            "Running tests",
+            "java.lang.IllegalStateException: Registry is frozen",
+            "    at net.minecraft.Registry.register(Registry.java:10)",
+            "    at com.example.Mod.init(Mod.java:5)",
+            "Tests finished",
+        ]);
+
+        assert_eq!(
+            extract_excerpt(&log, None, 2),
+            "java.lang.IllegalStateException: Registry is frozen\n    at net.minecraft.Registry.register(Registry.java:10)"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_tail_without_workflow_commands() {
+        let lines = (1..=20)
+            .map(|line| format!("line {line}"))
+            .collect::<Vec<_>>();
+        let log = format!("{}\n##[endgroup]\n[command]/usr/bin/git", lines.join("\n"));
+
+        let excerpt = extract_excerpt(&log, None, 10);
+
+        assert_eq!(excerpt, lines[20 - TAIL_LINES..].join("\n"));
+    }
+
+    #[test]
+    fn stays_within_the_limit() {
+        let log = format!("error: boom\n{}", vec!["x".repeat(100); 20].join("\n"));
+
+        assert!(extract_excerpt(&log, None, 20).len() <= EXCERPT_LIMIT);
+    }
+
+    #[test]
+    fn cant_close_the_code_block() {
+        let log = "error: ```injected";
+
+        assert_eq!(extract_excerpt(log, None, 1), "error: `\u{200b}``injected");
+    }
+}
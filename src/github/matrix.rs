@@ -0,0 +1,146 @@
+use crate::i18n::Locale;
+use crate::util::text::truncated_list;
+use crate::util::time::format_duration;
+use octocrab::models::workflows::{Conclusion, Job};
+
+/// Matrix jobs given a field of their own, any others are left out
+const GROUP_LIMIT: usize = 3;
+/// Legs listed one by one before a group is condensed to a summary
+const LEG_LIMIT: usize = 8;
+/// Longest a group's list of legs gets before it's condensed, well under Discord's 1024 per field
+const FIELD_LIMIT: usize = 512;
+
+struct Leg<'a> {
+    name: &'a str,
+    job: &'a Job,
+}
+
+/// One embed field per matrix job, listing how each of its legs went
+pub fn matrix_breakdown(locale: Locale, jobs: &[Job]) -> Vec<(String, String)> {
+    let mut groups: Vec<(&str, Vec<Leg>)> = Vec::new();
+
+    for job in jobs {
+        let Some((group, leg)) = split_leg(&job.name) else {
+            continue;
+        };
+
+        let leg = Leg { name: leg, job };
+
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, legs)) => legs.push(leg),
+            None => groups.push((group, vec![leg])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .take(GROUP_LIMIT)
+        .map(|(group, legs)| {
+            let passed = legs
+                .iter()
+                .filter(|leg| leg.job.conclusion == Some(Conclusion::Success))
+                .count();
+
+            let name = locale.format(
+                "matrix.passed",
+                &[
+                    ("group", &group),
+                    ("passed", &passed),
+                    ("total", &legs.len()),
+                ],
+            );
+            let listed = legs
+                .iter()
+                .map(|leg| format_leg(locale, leg))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            let value = if legs.len() <= LEG_LIMIT && listed.len() <= FIELD_LIMIT {
+                listed
+            } else {
+                condense(locale, &legs)
+            };
+
+            (name, value)
+        })
+        .collect()
+}
+
+/// Splits a matrix job's name into the job and its leg, GitHub names them `job (leg, values)`
+pub fn split_leg(name: &str) -> Option<(&str, &str)> {
+    name.strip_suffix(')')?.split_once(" (")
+}
+
+fn format_leg(locale: Locale, leg: &Leg) -> String {
+    let duration = leg
+        .job
+        .completed_at
+        .map(|completed_at| (completed_at - leg.job.started_at).num_seconds())
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| format!(" ({})", format_duration(locale, seconds)))
+        .unwrap_or_default();
+
+    // Only link the legs worth looking into, to keep the field short
+    match leg.job.conclusion {
+        Some(Conclusion::Success) => format!("✅ {}{duration}", leg.name),
+        _ => format!(
+            "{} [{}]({}){duration}",
+            icon(&leg.job.conclusion),
+            leg.name,
+            leg.job.html_url
+        ),
+    }
+}
+
+/// Only lists the legs that didn't succeed, as many as fit
+fn condense(locale: Locale, legs: &[Leg]) -> String {
+    let unsuccessful = legs
+        .iter()
+        .filter(|leg| leg.job.conclusion != Some(Conclusion::Success))
+        .collect::<Vec<&Leg>>();
+
+    if unsuccessful.is_empty() {
+        return format!(
+            "✅ {}",
+            locale.plural("matrix.all_passed", legs.len() as i64, &[])
+        );
+    }
+
+    let lines = unsuccessful
+        .iter()
+        .map(|leg| format!("{} {}", icon(&leg.job.conclusion), leg.name))
+        .collect::<Vec<String>>();
+
+    truncated_list(&lines, FIELD_LIMIT, locale)
+}
+
+fn icon(conclusion: &Option<Conclusion>) -> &'static str {
+    match conclusion {
+        Some(Conclusion::Success) => "✅",
+        Some(Conclusion::Failure) | Some(Conclusion::TimedOut) => "❌",
+        Some(Conclusion::Cancelled) => "🚫",
+        Some(Conclusion::Skipped) => "⏭️",
+        _ => "⚪",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_matrix_legs() {
+        assert_eq!(split_leg("test (ubuntu, 21)"), Some(("test", "ubuntu, 21")));
+        assert_eq!(
+            split_leg("Build mod (fabric (1.21))"),
+            Some(("Build mod", "fabric (1.21)"))
+        );
+    }
+
+    #[test]
+    fn leaves_other_jobs_alone() {
+        assert_eq!(split_leg("build"), None);
+        assert_eq!(split_leg("build (ubuntu"), None);
+        assert_eq!(split_leg("build(ubuntu)"), None);
+    }
+}
@@ -1,3 +1,13 @@
+pub mod aggregate;
+pub mod artifacts;
+pub mod attempts;
+pub mod commits;
+pub mod diagnostics;
+pub mod logs;
+pub mod matrix;
 pub mod octocrab;
+pub mod releases;
+pub mod threads;
+pub mod timing;
 pub mod verification;
 pub mod web;
@@ -1,4 +1,5 @@
-use octocrab::models::workflows::{Conclusion, Job, Run};
+use crate::builds::conclusions::RunConclusion;
+use octocrab::models::workflows::{Job, Run, WorkflowListArtifact};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -6,12 +7,26 @@ pub struct WorkflowRun {
     #[serde(flatten)]
     pub inner: Run,
     pub path: String,
+    pub run_attempt: i64,
     pub run_started_at: chrono::DateTime<chrono::Utc>,
     #[serde(rename = "conclusion")]
-    pub conclusion_enum: Option<Conclusion>,
+    pub conclusion_enum: Option<RunConclusion>,
 }
 
 #[derive(Deserialize)]
 pub struct JobsList {
     pub jobs: Vec<Job>,
 }
+
+#[derive(Deserialize)]
+pub struct ArtifactsList {
+    pub artifacts: Vec<WorkflowListArtifact>,
+}
+
+#[derive(Deserialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start_line: u32,
+    pub annotation_level: String,
+    pub message: String,
+}
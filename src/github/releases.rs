@@ -0,0 +1,177 @@
+use crate::app::App;
+use crate::config::repo_config::RepoSettings;
+use crate::error::Error;
+use crate::error::*;
+use crate::i18n::Locale;
+use octocrab::models::Repository;
+use octocrab::models::repos::Release;
+use octocrab::models::webhook_events::payload::{
+    ReleaseWebhookEventAction, ReleaseWebhookEventPayload,
+};
+use serenity::all::colours::branding;
+use serenity::all::*;
+use snafu::{OptionExt, ResultExt};
+
+/// Discord's limit for an embed description
+const DESCRIPTION_LIMIT: usize = 4096;
+/// Discord's limit for a button label
+const LABEL_LIMIT: usize = 80;
+const BUTTONS_PER_ROW: usize = 5;
+const MAX_ROWS: usize = 5;
+
+pub async fn handle_release(
+    app: &App,
+    config: RepoSettings,
+    locale: Locale,
+    channel_id: ChannelId,
+    repo: Option<Repository>,
+    payload: ReleaseWebhookEventPayload,
+) -> Result<(), Error> {
+    let Some(settings) = config.releases else {
+        return Ok(());
+    };
+
+    if !settings.actions.contains(&payload.action) {
+        return Ok(());
+    }
+
+    let release = serde_json::from_value::<Release>(payload.release)
+        .context(DeserializationErrorJsonSnafu)?;
+
+    if release.draft {
+        return Ok(());
+    }
+
+    let repo = repo.context(FailedToUnwrapValueSnafu)?;
+    let owner = repo.owner.context(FailedToUnwrapValueSnafu)?;
+    let channel_id = settings.channel_id.unwrap_or(channel_id);
+
+    let title = release
+        .name
+        .clone()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| release.tag_name.clone());
+
+    let header = format!(
+        "## {}\n{}: **{}**{}\n",
+        title,
+        locale.text("release.tag"),
+        release.tag_name,
+        if release.prerelease {
+            format!(" ({})", locale.text("release.prerelease"))
+        } else {
+            String::new()
+        }
+    );
+
+    let notes = release
+        .body
+        .as_deref()
+        .unwrap_or_default()
+        .replace("\r\n", "\n");
+    let limit = DESCRIPTION_LIMIT.saturating_sub(header.chars().count());
+    let description = header + &truncate_notes(locale, &notes, &release, limit);
+
+    let author = CreateEmbedAuthor::new(format!("{}/{}", repo.name, release.tag_name))
+        .icon_url(owner.avatar_url)
+        .url(release.html_url.clone());
+
+    let mut embed = CreateEmbed::new()
+        .author(author)
+        .description(description)
+        .color(if release.prerelease {
+            branding::YELLOW
+        } else {
+            branding::GREEN
+        });
+
+    if let Some(published_at) = release.published_at {
+        embed = embed.timestamp(Timestamp::from(published_at));
+    }
+
+    if let Some(release_author) = &release.author {
+        embed = embed.footer(
+            CreateEmbedFooter::new(&release_author.login)
+                .icon_url(release_author.avatar_url.as_str()),
+        );
+    }
+
+    let components = if settings.assets {
+        asset_buttons(&release)
+    } else {
+        Vec::new()
+    };
+
+    // Several actions can arrive at once for the same release, only one of them may announce it
+    let _release_lock = app.lock_release(release.id).await;
+
+    match app.cache.announced_releases.get(&release.id).await {
+        Some(message_id) => {
+            channel_id
+                .edit_message(
+                    &app.serenity_http,
+                    message_id,
+                    EditMessage::new().embed(embed).components(components),
+                )
+                .await
+                .context(FailedToSendMessageSnafu)?;
+        }
+        // Only edit releases we've announced, otherwise editing old releases would repost them
+        None if payload.action == ReleaseWebhookEventAction::Edited => {}
+        None => {
+            let message = channel_id
+                .send_message(
+                    &app.serenity_http,
+                    CreateMessage::new().embed(embed).components(components),
+                )
+                .await
+                .context(FailedToSendMessageSnafu)?;
+
+            app.cache
+                .announced_releases
+                .insert(release.id, message.id)
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+fn truncate_notes(locale: Locale, notes: &str, release: &Release, limit: usize) -> String {
+    if notes.chars().count() <= limit {
+        return notes.to_string();
+    }
+
+    let read_more = format!(
+        "…\n[{}]({})",
+        locale.text("release.read_more"),
+        release.html_url
+    );
+    let truncated = notes
+        .chars()
+        .take(limit.saturating_sub(read_more.chars().count()))
+        .collect::<String>();
+
+    truncated + &read_more
+}
+
+fn asset_buttons(release: &Release) -> Vec<CreateActionRow> {
+    release
+        .assets
+        .iter()
+        .take(BUTTONS_PER_ROW * MAX_ROWS)
+        .map(|asset| {
+            let label = asset
+                .label
+                .as_deref()
+                .filter(|label| !label.is_empty())
+                .unwrap_or(&asset.name);
+            let label = label.chars().take(LABEL_LIMIT).collect::<String>();
+
+            CreateButton::new_link(asset.browser_download_url.as_str()).label(label)
+        })
+        .collect::<Vec<CreateButton>>()
+        .chunks(BUTTONS_PER_ROW)
+        .map(|buttons| CreateActionRow::Buttons(buttons.to_vec()))
+        .collect()
+}
@@ -0,0 +1,63 @@
+use crate::app::{App, Forge};
+use crate::builds::threads::post_in_thread;
+use crate::error::Error;
+use crate::error::*;
+use crate::util::time::format_duration;
+use octocrab::models::webhook_events::payload::{
+    WorkflowJobWebhookEventAction, WorkflowJobWebhookEventPayload,
+};
+use octocrab::models::workflows::{Conclusion, Job};
+use serenity::all::CreateMessage;
+use snafu::ResultExt;
+
+/// Posts the result of each job into the thread of its build, if the build has one
+pub async fn handle_workflow_job(
+    app: &App,
+    payload: WorkflowJobWebhookEventPayload,
+) -> Result<(), Error> {
+    if payload.action != WorkflowJobWebhookEventAction::Completed {
+        return Ok(());
+    }
+
+    let job = serde_json::from_value::<Job>(payload.workflow_job)
+        .context(DeserializationErrorJsonSnafu)?;
+
+    let Some((thread_id, locale)) = app
+        .cache
+        .build_threads
+        .get(&(Forge::Github, job.run_id.into_inner()))
+        .await
+    else {
+        return Ok(());
+    };
+
+    let icon = match job.conclusion {
+        Some(Conclusion::Success) => "✅",
+        Some(Conclusion::Failure) | Some(Conclusion::TimedOut) => "❌",
+        _ => "⚪",
+    };
+
+    let duration = job
+        .completed_at
+        .map(|completed_at| (completed_at - job.started_at).num_seconds())
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| {
+            locale.format(
+                "build.in",
+                &[("duration", &format_duration(locale, seconds))],
+            )
+        })
+        .unwrap_or_default();
+
+    post_in_thread(
+        app,
+        thread_id,
+        CreateMessage::new().content(format!(
+            "{icon} [{}](<{}>){duration}",
+            job.name, job.html_url
+        )),
+    )
+    .await;
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::builds::timing::Timing;
+use crate::github::matrix::split_leg;
+use crate::i18n::Locale;
+use crate::util::text::truncated_list;
+use crate::util::time::format_duration;
+use chrono::{DateTime, Utc};
+use octocrab::models::workflows::{Conclusion, Job};
+
+/// Longest the list of jobs gets before the rest are summarized
+const FIELD_LIMIT: usize = 512;
+
+impl Timing {
+    /// Splits the time between the attempt starting and it finishing at the first job that ran.
+    /// Without jobs to go by, the run's own timestamps are all there is, so none of it counts as
+    /// queued.
+    pub fn of(started_at: DateTime<Utc>, finished_at: DateTime<Utc>, jobs: &[Job]) -> Self {
+        let total = (finished_at - started_at).num_seconds().max(0);
+        let first_job = jobs
+            .iter()
+            .filter(|job| job.conclusion != Some(Conclusion::Skipped))
+            .map(|job| job.started_at)
+            .min();
+
+        let queued = first_job
+            .map(|first_job| (first_job - started_at).num_seconds().clamp(0, total))
+            .unwrap_or_default();
+
+        Self {
+            queued,
+            run: total - queued,
+        }
+    }
+}
+
+/// Embed field with the duration of every job that isn't a matrix leg, those are broken down
+/// along with their matrix job
+pub fn job_durations(locale: Locale, jobs: &[Job]) -> Option<(String, String)> {
+    let jobs = jobs
+        .iter()
+        .filter(|job| split_leg(&job.name).is_none())
+        .filter(|job| job.conclusion != Some(Conclusion::Skipped))
+        .collect::<Vec<&Job>>();
+
+    // A single job takes as long as the run, which the status line already shows
+    if jobs.len() < 2 {
+        return None;
+    }
+
+    let lines = jobs
+        .iter()
+        .map(|job| {
+            let duration = job
+                .completed_at
+                .map(|completed_at| (completed_at - job.started_at).num_seconds())
+                .filter(|seconds| *seconds > 0)
+                .map(|seconds| format!(" — {}", format_duration(locale, seconds)))
+                .unwrap_or_default();
+
+            format!("[{}]({}){duration}", job.name, job.html_url)
+        })
+        .collect::<Vec<String>>();
+
+    Some((
+        locale.text("timing.jobs"),
+        truncated_list(&lines, FIELD_LIMIT, locale),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn time(minute: u32) -> DateTime<Utc> {
+        format!("2025-01-01T12:{minute:02}:00Z").parse().unwrap()
+    }
+
+    fn job(name: &str, conclusion: &str, started_at: u32, completed_at: u32) -> Job {
+        serde_json::from_value(json!({
+            "id": 1,
+            "run_id": 1,
+            "workflow_name": "Build",
+            "head_branch": "main",
+            "run_url": "https://api.github.com/repos/owner/repo/actions/runs/1",
+            "run_attempt": 1,
+            "node_id": "node",
+            "head_sha": "abc",
+            "url": "https://api.github.com/repos/owner/repo/actions/jobs/1",
+            "html_url": "https://github.com/owner/repo/actions/runs/1/job/1",
+            "status": "completed",
+            "conclusion": conclusion,
+            "created_at": time(0),
+            "started_at": time(started_at),
+            "completed_at": time(completed_at),
+            "name": name,
+            "steps": [],
+            "check_run_url": "https://api.github.com/repos/owner/repo/check-runs/1",
+            "labels": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn counts_the_wait_for_the_first_job_as_queued() {
+        let jobs = [
+            job("build", "success", 5, 20),
+            job("test", "success", 3, 25),
+        ];
+
+        let timing = Timing::of(time(0), time(30), &jobs);
+
+        assert_eq!(timing.queued, 3 * 60);
+        assert_eq!(timing.run, 27 * 60);
+    }
+
+    #[test]
+    fn ignores_skipped_jobs() {
+        let jobs = [
+            job("publish", "skipped", 1, 1),
+            job("build", "success", 4, 20),
+        ];
+
+        assert_eq!(Timing::of(time(0), time(20), &jobs).queued, 4 * 60);
+    }
+
+    #[test]
+    fn counts_nothing_as_queued_without_jobs() {
+        let timing = Timing::of(time(0), time(10), &[]);
+
+        assert_eq!(timing.queued, 0);
+        assert_eq!(timing.run, 10 * 60);
+    }
+
+    #[test]
+    fn keeps_the_split_within_the_run() {
+        // A re-run's jobs can have started before the attempt did
+        let early = Timing::of(time(10), time(20), &[job("build", "success", 5, 20)]);
+        assert_eq!((early.queued, early.run), (0, 10 * 60));
+
+        let backwards = Timing::of(time(20), time(10), &[job("build", "success", 30, 40)]);
+        assert_eq!((backwards.queued, backwards.run), (0, 0));
+    }
+
+    #[test]
+    fn leaves_matrix_legs_out_of_job_durations() {
+        let jobs = [
+            job("build", "success", 1, 5),
+            job("test (ubuntu, 21)", "success", 1, 5),
+            job("test (windows, 21)", "failure", 1, 5),
+            job("lint", "success", 1, 2),
+        ];
+
+        let (_, value) = job_durations(Locale::default(), &jobs).unwrap();
+
+        assert!(value.contains("[build]"));
+        assert!(value.contains("[lint]"));
+        assert!(!value.contains("ubuntu"));
+    }
+}
@@ -1,27 +1,35 @@
-use crate::config::app_config::Config;
+use crate::config::app_config::{Config, MirrorSettings};
 use crate::config::repo_config::RepoSettings;
 use crate::error::Error::InvalidSignature;
 use crate::error::{
-    DeserializationErrorJsonSnafu, Error, FailedToGetRepoConfigSnafu, InvalidBodySnafu,
-    InvalidConfigSnafu, InvalidHeaderSnafu, InvalidRepositorySnafu, InvalidSignatureHexSnafu,
-    MissingSignatureHeaderSnafu, MissingSignaturePrefixSnafu,
+    DeserializationErrorJsonSnafu, Error, FailedToGetRepoConfigSnafu, GithubSnafu,
+    InvalidBodySnafu, InvalidConfigSnafu, InvalidHeaderSnafu, InvalidRepositorySnafu,
+    InvalidSignatureHexSnafu, MissingSignatureHeaderSnafu, MissingSignaturePrefixSnafu,
 };
 use axum::body::Bytes;
 use axum::extract::{FromRequest, Request};
 use hmac_sha256::HMAC;
-use octocrab::models::repos::Content;
+use octocrab::models::RepositoryId;
+use octocrab::models::repos::Release;
 use octocrab::models::webhook_events::{WebhookEvent, WebhookEventPayload};
-use octocrab::models::workflows::Run;
+use octocrab::models::workflows::{Job, Run};
+use reqwest::StatusCode;
 use serenity::all::ChannelId;
 use snafu::{OptionExt, ResultExt};
 use subtle::ConstantTimeEq;
 
+#[derive(Clone)]
 pub struct GithubEvent {
+    /// Value of the `X-GitHub-Delivery` header, identifying this delivery in GitHub's logs
+    pub delivery_id: Option<String>,
     pub event: WebhookEvent,
     pub branch: String,
-    pub repo_config: RepoSettings,
+    pub repository_id: RepositoryId,
+    git_ref: Option<String>,
     pub channel_id: ChannelId,
     pub buttons: bool,
+    pub locale: Option<String>,
+    pub mirror: Option<MirrorSettings>,
 }
 
 impl<S> FromRequest<S> for GithubEvent
@@ -79,33 +87,78 @@ where
                     .ok()
                     .map(|r| r.head_branch)
             }
+            WebhookEventPayload::WorkflowJob(payload) => {
+                serde_json::from_value::<Job>(payload.workflow_job)
+                    .ok()
+                    .map(|j| j.head_branch)
+            }
+            WebhookEventPayload::Release(payload) => {
+                serde_json::from_value::<Release>(payload.release)
+                    .ok()
+                    .map(|r| r.target_commitish)
+            }
             _ => None,
         };
 
-        let octocrab = octocrab::instance();
-        let repo_handler = octocrab.repos_by_id(repo.id);
-
-        let mut builder = repo_handler.get_content().path(".ci-preview.yml");
-
-        if let Some(r#ref) = &git_ref {
-            builder = builder.r#ref(r#ref);
-        }
-
-        let repo_settings = builder
-            .send()
-            .await
-            .ok()
-            .and_then(|c| c.items.into_iter().next())
-            .and_then(|c| Content::decoded_content(&c))
-            .and_then(|c| serde_norway::from_str(&c).ok())
-            .context(FailedToGetRepoConfigSnafu)?;
+        let delivery_id = headers
+            .get("X-GitHub-Delivery")
+            .and_then(|header| header.to_str().ok())
+            .map(String::from);
 
         Ok(GithubEvent {
+            delivery_id,
             event,
-            branch: git_ref.unwrap_or_else(|| "[Unknown Branch]".into()),
-            repo_config: repo_settings,
+            branch: git_ref.clone().unwrap_or_else(|| "[Unknown Branch]".into()),
+            repository_id: repo.id,
+            git_ref,
             channel_id: config.channel_id,
             buttons: config.buttons,
+            locale: config.locale,
+            mirror: config.mirror,
         })
     }
 }
+
+impl GithubEvent {
+    /// Fetches the repository's `.ci-preview.yml` at the ref the event is about
+    pub async fn repo_config(&self) -> Result<RepoSettings, Error> {
+        file_contents(
+            self.repository_id,
+            ".ci-preview.yml",
+            self.git_ref.as_deref(),
+        )
+        .await?
+        .and_then(|c| RepoSettings::parse(&c))
+        .context(FailedToGetRepoConfigSnafu)
+    }
+}
+
+/// Fetches a file of the repository, `None` when it doesn't exist at the ref
+pub async fn file_contents(
+    repository_id: RepositoryId,
+    path: &str,
+    r#ref: Option<&str>,
+) -> Result<Option<String>, Error> {
+    let octocrab = octocrab::instance();
+    let repo_handler = octocrab.repos_by_id(repository_id);
+
+    let mut builder = repo_handler.get_content().path(path);
+
+    if let Some(r#ref) = r#ref {
+        builder = builder.r#ref(r#ref);
+    }
+
+    match builder.send().await {
+        Ok(content) => Ok(content
+            .items
+            .into_iter()
+            .next()
+            .and_then(|c| c.decoded_content())),
+        Err(octocrab::Error::GitHub { source, .. })
+            if source.status_code == StatusCode::NOT_FOUND =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e).context(GithubSnafu),
+    }
+}
@@ -1,41 +1,72 @@
-use crate::app::App;
+use crate::app::{App, Forge};
+use crate::builds::alerts::streak_summary;
+use crate::builds::changelog::format_commits;
+use crate::builds::commits::SKIP_PATTERNS;
+use crate::builds::conclusions::RunConclusion;
+use crate::builds::conclusions::{run_status, should_delete};
+use crate::builds::messages::{
+    BuildChannel, BuildEmbed, BuildMessage, FinalMessage, announce_running, build_links,
+    button_row, finish_build, finished_status, running_status,
+};
+use crate::builds::timing::{Timing, estimated_completion, timing_summary, usual_run_time};
+use crate::config::repo_config::ThreadMode;
 use crate::error::Error;
 use crate::error::*;
-use crate::github::octocrab::models::workflows_extension::{JobsList, WorkflowRun};
-use crate::github::verification::GithubEvent;
-use crate::util::time::format_duration;
+use crate::github::aggregate::{AggregateEvent, handle_aggregated};
+use crate::github::artifacts::{
+    BuildArtifacts, artifact_links, artifact_sources, build_artifacts, download_variables,
+};
+use crate::github::attempts::{format_attempts, previous_attempts};
+use crate::github::commits::resolve_commits;
+use crate::github::diagnostics::{failure_diagnostics, fetch_jobs};
+use crate::github::logs::failed_job_log;
+use crate::github::matrix::matrix_breakdown;
+use crate::github::octocrab::models::workflows_extension::WorkflowRun;
+use crate::github::releases::handle_release;
+use crate::github::threads::handle_workflow_job;
+use crate::github::timing::job_durations;
+use crate::github::verification::{GithubEvent, file_contents};
+use crate::history::store::{Build, BuildStatus};
+use crate::i18n::Locale;
+use crate::mirror::archive::{mirror_artifacts, mirror_variables};
+use crate::queue::worker::Delivery;
 use axum::extract::State;
+use axum::http::StatusCode;
 use octocrab::models::webhook_events::WebhookEventPayload;
 use octocrab::models::webhook_events::payload::WorkflowRunWebhookEventAction;
-use octocrab::models::workflows::Conclusion;
 use regex::Regex;
-use serenity::all::colours::branding;
 use serenity::all::*;
 use snafu::{OptionExt, ResultExt};
-
-const SKIP_PATTERNS: &[&str] = &[
-    "[skip ci]",
-    "[ci skip]",
-    "[no ci]",
-    "[skip actions]",
-    "[actions skip]",
-];
+use tracing::log::error;
 
 pub async fn handle_github_webhhook(
     State(app): State<App>,
     event: GithubEvent,
-) -> Result<&'static str, Error> {
-    match event.event.specific {
+) -> Result<(StatusCode, &'static str), Error> {
+    if !app.queue.enqueue(Delivery::Github(Box::new(event))) {
+        return Err(Error::QueueFull);
+    }
+
+    Ok((StatusCode::ACCEPTED, "Thanks and so long for all fish"))
+}
+
+/// Handles a verified webhook event, called by the queue's workers
+pub async fn process_event(app: App, event: GithubEvent) -> Result<(), Error> {
+    match event.event.specific.clone() {
         WebhookEventPayload::Push(payload) => {
-            let message = payload
-                .head_commit
-                .context(FailedToUnwrapValueSnafu)?
-                .message;
+            // Deleting a branch is a push without a head commit, there's nothing to remember
+            let Some(head_commit) = payload.head_commit.filter(|_| !payload.deleted) else {
+                return Ok(());
+            };
+            let message = head_commit.message;
 
             if !SKIP_PATTERNS.iter().any(|skip| message.contains(skip)) {
                 app.cache
                     .commits
-                    .insert(payload.after, payload.commits)
+                    .insert(
+                        payload.after,
+                        payload.commits.into_iter().map(Into::into).collect(),
+                    )
                     .await
             }
         }
@@ -43,236 +74,458 @@ pub async fn handle_github_webhhook(
             let workflow = serde_json::from_value::<WorkflowRun>(payload.workflow_run)
                 .context(DeserializationErrorJsonSnafu)?;
 
-            let config = event.repo_config;
+            let config = event.repo_config().await?;
+
+            // Only configured workflows get a message, so the rest shouldn't spend any rate limit
+            if !config.workflows.contains(&workflow.path) {
+                return Ok(());
+            }
+
+            let locale = Locale::resolve([config.locale.as_deref(), event.locale.as_deref()]);
             let repo = event.event.repository.context(FailedToUnwrapValueSnafu)?;
             let branch = event.branch;
 
             // TODO - Rethink
             let sender = event.event.sender.context(FailedToUnwrapValueSnafu)?;
-            let owner = repo.owner.context(FailedToUnwrapValueSnafu)?;
-            let head_sha = workflow.inner.head_sha;
+            let owner = repo.owner.clone().context(FailedToUnwrapValueSnafu)?;
+            let head_sha = workflow.inner.head_sha.clone();
             let run_number = workflow.inner.run_number;
+            let repository = repo
+                .full_name
+                .clone()
+                .unwrap_or_else(|| format!("{}/{}", owner.login, repo.name));
+
+            // Deliveries can arrive in any order, so handle one event per run at a time
+            let run = (Forge::Github, *workflow.inner.id);
+            let _run_lock = app.lock_run(run).await;
+
+            let recorded = app.history.build(&repository, *workflow.inner.id).await?;
+
+            // The run already has its final message, anything after that is late or a duplicate
+            if recorded.as_ref().is_some_and(|recorded| {
+                recorded.status == BuildStatus::Completed
+                    && recorded.run_attempt >= workflow.run_attempt
+            }) {
+                return Ok(());
+            }
+
+            let regex = Regex::new(&config.mod_version.regex).context(InvalidRegexSnafu)?;
 
             // TODO - This is incredibly bad
-            let mod_version = octocrab::instance()
-                .repos_by_id(repo.id)
-                .get_content()
-                .path(&config.mod_version.path)
-                .r#ref(&branch)
-                .send()
-                .await
-                .ok()
-                .and_then(|c| c.items.into_iter().next())
-                .and_then(|c| octocrab::models::repos::Content::decoded_content(&c))
-                .and_then(|c| {
-                    let regex = Regex::new(&config.mod_version.regex).ok()?;
-                    regex
-                        .captures(&c)
-                        .and_then(|c| c.get(config.mod_version.group))
-                        .map(|c| c.as_str().to_string())
-                })
+            let mod_version = file_contents(repo.id, &config.mod_version.path, Some(&branch))
+                .await?
+                .and_then(|c| config.mod_version.capture(&regex, &c))
                 .context(FailedToUnwrapValueSnafu)?;
 
-            let formatted_mod_version = config
-                .mod_version
-                .format
-                .clone()
-                .unwrap_or_else(|| mod_version.to_string())
-                .replace("${mod_version}", &mod_version)
-                .replace("${minecraft_version}", &config.minecraft_version)
-                .replace("${build_number}", &run_number.to_string());
-
-            let commit_info = match app.cache.commits.get(&head_sha).await {
-                Some(commits) if !commits.is_empty() => {
-                    let mut formatted_commits = commits
-                        .iter()
-                        .map(|commit| {
-                            let commiter = &commit.committer;
-                            let username = commiter.username.clone().unwrap();
-                            let title = commit
-                                .message
-                                .split("\n")
-                                .next()
-                                .unwrap()
-                                .replace("#", "\\#");
-                            format!(
-                                "[➤]({}) {} - [{}](https://github.com/{})",
-                                commit.url, title, username, username
-                            )
-                        })
-                        .collect::<Vec<String>>()
-                        .join("\n");
-
-                    // TODO - Magic number :(
-                    if formatted_commits.len() > 3072 {
-                        let url = repo.html_url.context(FailedToUnwrapValueSnafu)?;
-                        formatted_commits = format!(
-                            "Commit list is too long to display, please look [here]({url}/commits/{head_sha}) instead."
-                        )
-                    }
-
-                    formatted_commits
+            let formatted_mod_version =
+                config
+                    .mod_version
+                    .display(&mod_version, &config.minecraft_version, run_number);
+
+            let build_number = run_number.to_string();
+            let variables = [
+                ("${version}", formatted_mod_version.as_str()),
+                ("${mod_version}", mod_version.as_str()),
+                ("${minecraft_version}", config.minecraft_version.as_str()),
+                ("${build_number}", build_number.as_str()),
+            ];
+
+            let commits = resolve_commits(&app, &repo, &branch, &workflow, recorded.as_ref()).await;
+
+            let commits_link = repo.html_url.as_ref().map(|url| {
+                let url = format!("{url}/commits/{head_sha}");
+                locale.format("build.too_many_commits", &[("url", &url)])
+            });
+            let commit_info = match format_commits(locale, &commits, config.changelog.as_ref()) {
+                formatted_commits if formatted_commits.is_empty() => {
+                    locale.text("build.no_commits")
                 }
-                _ => "No commits found".into(),
+                formatted_commits => formatted_commits,
             };
 
-            if config.workflows.contains(&workflow.path) {
-                let embed = |status, extra| {
-                    let author = CreateEmbedAuthor::new(format!("{}/{}", repo.name, branch))
-                        .icon_url(owner.avatar_url)
-                        .url(workflow.inner.html_url);
-
-                    let description = format!(
-                        r"
-                        ## Build <t:{}:R>
-                        Status: {}
-                        Version: **{}**
-                        {}{}",
-                        workflow.run_started_at.timestamp(),
-                        status,
-                        formatted_mod_version,
-                        extra,
-                        commit_info
-                    );
+            let build = Build {
+                repository,
+                branch: branch.clone(),
+                workflow: workflow.path.clone(),
+                run_id: *workflow.inner.id,
+                run_attempt: workflow.run_attempt,
+                run_number,
+                head_sha: head_sha.clone(),
+                version: formatted_mod_version.clone(),
+                status: BuildStatus::InProgress,
+                conclusion: None,
+                started_at: workflow.run_started_at,
+                completed_at: None,
+                duration: None,
+                commits: commits.clone(),
+                url: workflow.inner.html_url.to_string(),
+                message_link: None,
+                links: Vec::new(),
+                message_id: None,
+                queued: None,
+                // Better kept off public pages than published when the payload doesn't say
+                private: repo.private.unwrap_or(true),
+            };
 
-                    let footer = CreateEmbedFooter::new(sender.login).icon_url(sender.avatar_url);
+            let channel = BuildChannel {
+                forge: Forge::Github,
+                config: &config,
+                locale,
+                channel_id: event.channel_id,
+            };
 
-                    CreateEmbed::new()
-                        .author(author)
-                        .description(description)
-                        .footer(footer)
-                        .color(branding::BLURPLE)
+            // Re-runs reuse the run's message, listing how the earlier attempts went
+            let previous_attempts = previous_attempts(&app, &build).await;
+            let attempt_history = format_attempts(locale, &previous_attempts);
+            let previous_message = previous_attempts
+                .iter()
+                .rev()
+                .find_map(|attempt| attempt.message_id)
+                .map(MessageId::new);
+
+            // Artifacts only exist once the run is done, and buttons aren't shown before that
+            let downloads = if payload.action == WorkflowRunWebhookEventAction::Completed {
+                let mut downloads =
+                    download_variables(&app, &workflow, &build.repository, &config).await;
+                downloads.extend(mirror_variables(
+                    event.mirror.as_ref(),
+                    &config,
+                    workflow.inner.html_url.as_str(),
+                    &build,
+                ));
+                downloads
+            } else {
+                Vec::new()
+            };
+            let variables = variables
+                .into_iter()
+                .chain(
+                    downloads
+                        .iter()
+                        .map(|(placeholder, url)| (placeholder.as_str(), url.as_str())),
+                )
+                .collect::<Vec<(&str, &str)>>();
+
+            let build_embed = BuildEmbed {
+                locale,
+                author: CreateEmbedAuthor::new(format!("{}/{}", repo.name, branch))
+                    .icon_url(owner.avatar_url)
+                    .url(workflow.inner.html_url.clone()),
+                footer: Some(CreateEmbedFooter::new(sender.login).icon_url(sender.avatar_url)),
+                started_at: workflow.run_started_at,
+                version: formatted_mod_version.clone(),
+                commits: commit_info,
+                commits_link,
+            };
+
+            if config.aggregate.is_some() {
+                let aggregate_event = AggregateEvent {
+                    action: payload.action,
+                    workflow: &workflow,
+                    build,
+                    channel_id: event.channel_id,
+                    buttons: event.buttons.then(|| button_row(&config, &variables)),
+                    links: build_links(&config, &variables),
+                    locale,
+                    mirror: event.mirror.clone(),
                 };
 
-                match payload.action {
-                    WorkflowRunWebhookEventAction::InProgress => {
-                        let emoji = app
-                            .serenity_http
-                            .get_application_emoji(app.emojis.processing)
-                            .await
-                            .context(FailedToFindEmojiSnafu)?;
-                        let status = &format!("Build is running for **#{}** {}", run_number, emoji);
-
-                        let message = event
-                            .channel_id
-                            .send_message(
-                                app.serenity_http,
-                                CreateMessage::new().embed(embed(status, "")),
-                            )
-                            .await
-                            .context(FailedToSendMessageSnafu)?;
+                return handle_aggregated(&app, &config, aggregate_event, |status, extra| {
+                    build_embed.embed(status, extra)
+                })
+                .await;
+            }
 
-                        app.cache
-                            .running_workflows
-                            .insert(workflow.inner.id, message.id)
-                            .await;
+            match payload.action {
+                WorkflowRunWebhookEventAction::InProgress => {
+                    if app.cache.running_workflows.contains_key(&run) {
+                        return Ok(());
                     }
-                    WorkflowRunWebhookEventAction::Completed => {
-                        let message_id = app
-                            .cache
-                            .running_workflows
-                            .get(&workflow.inner.id)
-                            .await
-                            .context(CannotFindMessageSnafu)?;
-                        let mut message = event
-                            .channel_id
-                            .message(&app.serenity_http, message_id)
-                            .await
-                            .context(FailedToSendMessageSnafu)?;
-
-                        let time_taken = {
-                            let difference = workflow.inner.updated_at - workflow.inner.created_at;
-                            format_duration(difference.num_seconds())
-                        };
-
-                        let mut edit = EditMessage::new();
-
-                        let mut logs = None;
-                        let (run_status, color) = match workflow.conclusion_enum {
-                            Some(Conclusion::Success) => {
-                                if event.buttons {
-                                    let mut buttons = Vec::new();
-
-                                    for (id, button) in config.buttons {
-                                        let url = button
-                                            .url
-                                            .clone()
-                                            .map(|i| {
-                                                i.replace("${version}", &formatted_mod_version)
-                                                    .replace("${mod_version}", &mod_version)
-                                                    .replace(
-                                                        "${minecraft_version}",
-                                                        &config.minecraft_version,
-                                                    )
-                                                    .replace(
-                                                        "${build_number}",
-                                                        &run_number.to_string(),
-                                                    )
-                                            })
-                                            .unwrap_or_default();
-
-                                        buttons.push(button.convert(&id, &url))
-                                    }
-
-                                    let action_row = CreateActionRow::Buttons(buttons);
-
-                                    edit = edit.components(vec![action_row]);
-                                }
-
-                                let emoji = app
-                                    .serenity_http
-                                    .get_application_emoji(app.emojis.success)
-                                    .await
-                                    .context(FailedToFindEmojiSnafu)?;
-                                (format!("{emoji} Success"), branding::GREEN)
+
+                    let status =
+                        running_status(&app, locale, workflow.run_attempt, run_number).await?;
+                    let estimate = usual_run_time(&app, &build)
+                        .await
+                        .map(|usual| {
+                            let eta =
+                                estimated_completion(locale, workflow.inner.updated_at, usual);
+                            format!("-# {eta}\n")
+                        })
+                        .unwrap_or_default();
+                    let embed = build_embed.embed(&status, &(attempt_history + &estimate));
+
+                    let message_id =
+                        announce_running(&app, channel, previous_message, embed, build).await?;
+
+                    app.cache.running_workflows.insert(run, message_id).await;
+
+                    app.cache
+                        .announced_builds
+                        .insert((repo.id, branch.clone()), head_sha.clone())
+                        .await;
+                }
+                WorkflowRunWebhookEventAction::Completed => {
+                    // Without a running message, in_progress was never seen or got lost, so
+                    // the final embed is posted as a new message instead
+                    let running_message = app
+                        .cache
+                        .running_workflows
+                        .get(&run)
+                        .await
+                        .or(previous_message);
+
+                    // A re-run's run was created along with the first attempt
+                    let started_at = if workflow.run_attempt > 1 {
+                        workflow.run_started_at
+                    } else {
+                        workflow.inner.created_at
+                    };
+                    let difference = workflow.inner.updated_at - started_at;
+
+                    let build = Build {
+                        status: BuildStatus::Completed,
+                        conclusion: workflow
+                            .conclusion_enum
+                            .as_ref()
+                            .and_then(|c| serde_json::to_value(c).ok())
+                            .and_then(|c| c.as_str().map(String::from)),
+                        completed_at: Some(workflow.inner.updated_at),
+                        duration: Some(difference.num_seconds()),
+                        ..build
+                    };
+
+                    let conclusion_settings = workflow
+                        .conclusion_enum
+                        .and_then(|conclusion| config.conclusions.get(&conclusion));
+
+                    if should_delete(&app, &build, conclusion_settings).await {
+                        if let Some(message_id) = running_message
+                            && let Err(e) = event
+                                .channel_id
+                                .delete_message(&app.serenity_http, message_id)
+                                .await
+                        {
+                            error!("Failed to delete build message: {e}");
+                        }
+
+                        if let Err(e) = app.history.record(build).await {
+                            error!("Failed to record build: {e}");
+                        }
+
+                        app.cache.running_workflows.remove(&run).await;
+                        return Ok(());
+                    }
+
+                    let mut components = None;
+
+                    let mut logs = None;
+                    let mut failed_log = None;
+                    let mut links = Vec::new();
+                    let mut artifacts = BuildArtifacts::default();
+
+                    // The message is worth more than the details the jobs add to it
+                    let jobs = fetch_jobs(&app, workflow.inner.jobs_url.as_str())
+                        .await
+                        .inspect_err(|e| error!("Failed to fetch jobs: {e}"))
+                        .unwrap_or_default();
+
+                    // Queue time says more about the runners than the build, so it's kept
+                    // out of the duration shown and compared
+                    let timing = Timing::of(started_at, workflow.inner.updated_at, &jobs);
+                    let usual = match workflow.conclusion_enum {
+                        Some(RunConclusion::Success) => usual_run_time(&app, &build).await,
+                        _ => None,
+                    };
+                    let build = Build {
+                        queued: (!jobs.is_empty()).then_some(timing.queued),
+                        ..build
+                    };
+
+                    match workflow.conclusion_enum {
+                        Some(RunConclusion::Success) => {
+                            if event.buttons {
+                                components = Some(vec![button_row(&config, &variables)]);
                             }
-                            Some(Conclusion::Failure) => {
-                                let jobs_list = app
-                                    .https
-                                    .get(workflow.inner.jobs_url)
-                                    .header(
-                                        "User-Agent",
-                                        "CI-Preview (https://github.com/IThundxr/CI-Preview)",
+
+                            links = build_links(&config, &variables);
+
+                            if let Some(settings) = &config.artifacts {
+                                artifacts =
+                                    build_artifacts(&app, &workflow, &build.repository, settings)
+                                        .await;
+                            }
+                        }
+                        Some(RunConclusion::Failure | RunConclusion::TimedOut) => {
+                            logs = match failure_diagnostics(&app, locale, &jobs).await {
+                                Some(diagnostics) => Some(format!("{diagnostics}\n")),
+                                None => jobs.first().map(|job| {
+                                    let url = job.html_url.as_str();
+                                    format!(
+                                        "{}\n",
+                                        locale.format("build.run_logs", &[("url", &url)])
                                     )
-                                    .send()
-                                    .await
-                                    .context(ReqwestSnafu)?
-                                    .json::<JobsList>()
-                                    .await
-                                    .context(ReqwestSnafu)?;
-
-                                logs = jobs_list
-                                    .jobs
-                                    .first()
-                                    .map(|i| i.html_url.as_str())
-                                    .map(|i| format!("Logs: [Run Logs]({i})\n"));
-
-                                let emoji = app
-                                    .serenity_http
-                                    .get_application_emoji(app.emojis.failed)
-                                    .await
-                                    .context(FailedToFindEmojiSnafu)?;
-                                (format!("{emoji} Failed"), branding::RED)
+                                }),
+                            };
+
+                            failed_log = failed_job_log(&app, &jobs, &config.logs).await;
+                        }
+                        _ => {}
+                    }
+
+                    let (run_status, color) =
+                        run_status(&app, locale, workflow.conclusion_enum, conclusion_settings)
+                            .await?;
+
+                    let status = finished_status(
+                        locale,
+                        &run_status,
+                        workflow.run_attempt,
+                        run_number,
+                        timing.run,
+                    );
+
+                    let streak = app
+                        .history
+                        .failing_streak(
+                            &build.repository,
+                            &build.branch,
+                            &build.workflow,
+                            build.run_id,
+                        )
+                        .await
+                        .inspect_err(|e| error!("Failed to find failing streak: {e}"))
+                        .unwrap_or_default();
+
+                    let previous = app
+                        .history
+                        .previous_build(
+                            &build.repository,
+                            &build.branch,
+                            &build.workflow,
+                            build.run_id,
+                        )
+                        .await
+                        .inspect_err(|e| error!("Failed to find previous build: {e}"))
+                        .ok()
+                        .flatten();
+
+                    let extra = attempt_history
+                        + &streak_summary(locale, &build, &streak, previous.as_ref())
+                            .map(|summary| format!("-# {summary}\n"))
+                            .unwrap_or_default()
+                        + &timing_summary(locale, timing, usual)
+                            .map(|summary| format!("-# {summary}\n"))
+                            .unwrap_or_default()
+                        + &logs.unwrap_or_default();
+
+                    let mut fields = job_durations(locale, &jobs)
+                        .into_iter()
+                        .chain(matrix_breakdown(locale, &jobs))
+                        .chain(artifact_links(locale, &artifacts.links))
+                        .collect::<Vec<(String, String)>>();
+
+                    let failed = matches!(
+                        workflow.conclusion_enum,
+                        Some(RunConclusion::Failure | RunConclusion::TimedOut)
+                    );
+                    let threads = config
+                        .threads
+                        .as_ref()
+                        .filter(|threads| threads.mode == ThreadMode::All || failed);
+
+                    let mut attachments = artifacts.attachments;
+                    let mut follow_up = None;
+
+                    // Keep the log in the thread when there is one, rather than on the build message
+                    if let Some(log) = failed_log {
+                        if threads.is_some() {
+                            let mut message = CreateMessage::new();
+
+                            if let Some(excerpt) = &log.excerpt {
+                                message = message.content(format!(
+                                    "{}\n```\n{excerpt}\n```",
+                                    locale
+                                        .format("build.log_excerpt_for", &[("job", &log.job_name)])
+                                ));
                             }
-                            _ => ("".into(), branding::BLURPLE),
-                        };
 
-                        let status =
-                            &format!("**{} #{}** in {}", run_status, run_number, time_taken);
+                            if let Some(attachment) = log.attachment {
+                                message = message.add_file(attachment);
+                            }
 
-                        let embed = embed(status, &logs.unwrap_or_default()).color(color);
-                        edit = edit.embed(embed);
+                            follow_up = Some(message);
+                        } else {
+                            if let Some(excerpt) = &log.excerpt {
+                                fields.push((
+                                    locale.text("build.log_excerpt"),
+                                    format!("```\n{excerpt}\n```"),
+                                ));
+                            }
+
+                            attachments.extend(log.attachment);
+                        }
+                    }
+
+                    let embed = build_embed.fit(&status, &extra, fields).color(color);
+                    let message = FinalMessage {
+                        message_id: running_message,
+                        message: BuildMessage {
+                            embed,
+                            components: components.unwrap_or_default(),
+                            attachments,
+                        },
+                        status,
+                        threads,
+                        follow_up,
+                    };
+                    let build = Build { links, ..build };
 
-                        let _ = message.edit(app.serenity_http, edit).await;
+                    let (build, sent) =
+                        finish_build(&app, channel, message, build, previous.as_ref()).await?;
 
-                        app.cache.running_workflows.remove(&workflow.inner.id).await;
+                    if sent {
+                        app.cache
+                            .announced_builds
+                            .insert((repo.id, branch.clone()), head_sha.clone())
+                            .await;
+                    }
+
+                    if workflow.conclusion_enum == Some(RunConclusion::Success)
+                        && let Some(mirror) = &event.mirror
+                    {
+                        mirror_artifacts(
+                            &app,
+                            mirror,
+                            &build,
+                            artifact_sources(&app, &workflow).await,
+                        )
+                        .await;
                     }
-                    _ => {}
+
+                    app.cache.running_workflows.remove(&run).await;
                 }
+                _ => {}
             }
         }
+        WebhookEventPayload::WorkflowJob(payload) => {
+            handle_workflow_job(&app, *payload).await?;
+        }
+        WebhookEventPayload::Release(payload) => {
+            let config = event.repo_config().await?;
+            let locale = Locale::resolve([config.locale.as_deref(), event.locale.as_deref()]);
+
+            handle_release(
+                &app,
+                config,
+                locale,
+                event.channel_id,
+                event.event.repository,
+                *payload,
+            )
+            .await?;
+        }
         _ => {}
     }
 
-    Ok("Thanks and so long for all fish")
+    Ok(())
 }
@@ -0,0 +1,83 @@
+use crate::app::App;
+use crate::config::repo_config::RepoSettings;
+use crate::error::{Error, FailedToGetRepoConfigSnafu, ReqwestSnafu};
+use crate::gitlab::models::{PipelineJob, Project};
+use crate::mirror::archive::ArtifactSource;
+use reqwest::{StatusCode, Url};
+use snafu::{OptionExt, ResultExt};
+
+/// Fetches a file of the project at the given ref, `None` when it doesn't exist
+pub async fn raw_file(
+    app: &App,
+    project: &Project,
+    path: &str,
+    r#ref: &str,
+) -> Result<Option<String>, Error> {
+    let Some(url) = file_url(project, path, r#ref) else {
+        return Ok(None);
+    };
+
+    let response = app.gitlab_get(url).send().await.context(ReqwestSnafu)?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let contents = response
+        .error_for_status()
+        .context(ReqwestSnafu)?
+        .text()
+        .await
+        .context(ReqwestSnafu)?;
+
+    Ok(Some(contents))
+}
+
+fn file_url(project: &Project, path: &str, r#ref: &str) -> Option<Url> {
+    let mut url = project.api_url()?;
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        // The path is a single segment, so its slashes end up encoded as GitLab expects
+        .extend([
+            "projects",
+            &project.id.to_string(),
+            "repository",
+            "files",
+            path,
+            "raw",
+        ]);
+    url.query_pairs_mut().append_pair("ref", r#ref);
+
+    Some(url)
+}
+
+/// Fetches the project's `.ci-preview.yml` at the given ref
+pub async fn repo_config(app: &App, project: &Project, r#ref: &str) -> Result<RepoSettings, Error> {
+    raw_file(app, project, ".ci-preview.yml", r#ref)
+        .await?
+        .and_then(|c| RepoSettings::parse(&c))
+        .context(FailedToGetRepoConfigSnafu)
+}
+
+/// Artifacts of the pipeline's jobs, named after their job, for the mirror to copy
+pub fn artifact_sources(app: &App, project: &Project, jobs: &[PipelineJob]) -> Vec<ArtifactSource> {
+    jobs.iter()
+        .filter(|job| job.has_artifacts())
+        .filter_map(|job| {
+            let mut url = project.api_url()?;
+            url.path_segments_mut().ok()?.pop_if_empty().extend([
+                "projects",
+                &project.id.to_string(),
+                "jobs",
+                &job.id.to_string(),
+                "artifacts",
+            ]);
+
+            Some(ArtifactSource {
+                name: job.name.clone(),
+                download: app.gitlab_get(url),
+            })
+        })
+        .collect()
+}
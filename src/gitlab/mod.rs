@@ -0,0 +1,4 @@
+pub mod client;
+pub mod models;
+pub mod verification;
+pub mod web;
@@ -0,0 +1,239 @@
+use crate::builds::commits::{CommitInfo, CommitUser};
+use crate::builds::conclusions::RunConclusion;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Deserializer};
+
+/// `visibility_level` of projects anyone can see
+const PUBLIC_VISIBILITY: u8 = 20;
+
+/// Body of a webhook delivery, told apart by its `object_kind`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "object_kind", rename_all = "snake_case")]
+pub enum GitlabPayload {
+    Push(PushEvent),
+    Pipeline(Box<PipelineEvent>),
+    /// Any other kind of event the webhook is set up to send
+    #[serde(other)]
+    Ignored,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Project {
+    pub id: u64,
+    pub name: String,
+    /// Full name of the project, e.g. `group/project`
+    pub path_with_namespace: String,
+    pub web_url: String,
+    pub avatar_url: Option<String>,
+    /// 0 for private, 10 for internal and 20 for public projects
+    pub visibility_level: Option<u8>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PushEvent {
+    /// SHA of the branch's head after the push
+    pub after: String,
+    #[serde(default)]
+    pub commits: Vec<PushCommit>,
+    pub project: Project,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PushCommit {
+    pub id: String,
+    pub message: String,
+    pub url: String,
+    pub author: CommitAuthor,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PipelineEvent {
+    pub object_attributes: Pipeline,
+    pub user: Option<User>,
+    pub project: Project,
+    pub commit: Option<PushCommit>,
+    /// The pipeline's jobs, only the latest of each when some were retried
+    #[serde(default)]
+    pub builds: Vec<PipelineJob>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Pipeline {
+    pub id: u64,
+    /// Number of the pipeline within the project
+    pub iid: Option<i64>,
+    pub r#ref: String,
+    pub sha: String,
+    pub status: PipelineStatus,
+    #[serde(deserialize_with = "timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "optional_timestamp")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Seconds the pipeline waited for a runner
+    pub queued_duration: Option<f64>,
+    /// Only sent by newer GitLab versions
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PipelineJob {
+    pub id: u64,
+    pub name: String,
+    pub status: PipelineStatus,
+    #[serde(default, deserialize_with = "optional_timestamp")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "optional_timestamp")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Failures of jobs that are allowed to fail don't fail the pipeline
+    #[serde(default)]
+    pub allow_failure: bool,
+    pub artifacts_file: Option<ArtifactsFile>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ArtifactsFile {
+    /// Missing when the job didn't upload any artifacts
+    pub filename: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct User {
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStatus {
+    Running,
+    Success,
+    Failed,
+    Canceled,
+    Skipped,
+    /// Created, pending, waiting for manual action and the like, none of which get a message
+    #[serde(other)]
+    Waiting,
+}
+
+impl GitlabPayload {
+    pub fn project(&self) -> Option<&Project> {
+        match self {
+            Self::Push(push) => Some(&push.project),
+            Self::Pipeline(pipeline) => Some(&pipeline.project),
+            Self::Ignored => None,
+        }
+    }
+}
+
+impl Project {
+    /// Base url of the REST API of the GitLab instance the project lives on
+    pub fn api_url(&self) -> Option<Url> {
+        // Instances can be served under a sub-path, which is what's left of the project's url
+        let base = self
+            .web_url
+            .trim_end_matches('/')
+            .strip_suffix(&self.path_with_namespace)?;
+
+        Url::parse(base).ok()?.join("api/v4/").ok()
+    }
+
+    /// Whether anyone can see the project, internal ones need an account on the instance
+    pub fn is_public(&self) -> bool {
+        self.visibility_level == Some(PUBLIC_VISIBILITY)
+    }
+}
+
+impl PipelineJob {
+    pub fn web_url(&self, project: &Project) -> String {
+        format!(
+            "{}/-/jobs/{}",
+            project.web_url.trim_end_matches('/'),
+            self.id
+        )
+    }
+
+    pub fn has_artifacts(&self) -> bool {
+        self.artifacts_file
+            .as_ref()
+            .is_some_and(|file| file.filename.is_some())
+    }
+}
+
+impl Pipeline {
+    pub fn web_url(&self, project: &Project) -> String {
+        self.url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/-/pipelines/{}",
+                project.web_url.trim_end_matches('/'),
+                self.id
+            )
+        })
+    }
+
+    /// The pipeline's number within the project, falling back to its global id on older
+    /// GitLab versions
+    pub fn number(&self) -> i64 {
+        self.iid.unwrap_or(self.id as i64)
+    }
+}
+
+impl PipelineStatus {
+    /// The conclusion the pipeline is shown and recorded with, once it's done
+    pub fn conclusion(self) -> Option<RunConclusion> {
+        match self {
+            Self::Success => Some(RunConclusion::Success),
+            Self::Failed => Some(RunConclusion::Failure),
+            Self::Canceled => Some(RunConclusion::Cancelled),
+            Self::Skipped => Some(RunConclusion::Skipped),
+            Self::Running | Self::Waiting => None,
+        }
+    }
+}
+
+impl From<PushCommit> for CommitInfo {
+    fn from(commit: PushCommit) -> Self {
+        Self {
+            id: commit.id,
+            url: commit.url,
+            message: commit.message,
+            // GitLab usernames mean nothing on GitHub, so authors aren't linked
+            author: CommitUser::new(Some(commit.author.name), commit.author.email, None),
+        }
+    }
+}
+
+/// GitLab sends timestamps like `2016-08-12 15:23:28 UTC` in webhooks, and RFC 3339 elsewhere
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S UTC")
+                .map(|timestamp| timestamp.and_utc())
+        })
+        .ok()
+}
+
+fn timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    parse_timestamp(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp: {value}")))
+}
+
+fn optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) => parse_timestamp(&value)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp: {value}"))),
+        None => Ok(None),
+    }
+}
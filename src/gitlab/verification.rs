@@ -0,0 +1,90 @@
+use crate::config::app_config::{Config, MirrorSettings};
+use crate::error::{
+    DeserializationErrorJsonSnafu, Error, InvalidBodySnafu, InvalidConfigSnafu,
+    InvalidGitlabTokenSnafu, InvalidHeaderSnafu,
+};
+use crate::gitlab::models::GitlabPayload;
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use serenity::all::ChannelId;
+use snafu::{OptionExt, ResultExt};
+use subtle::ConstantTimeEq;
+
+#[derive(Clone)]
+pub struct GitlabEvent {
+    /// Value of the `X-Gitlab-Event-UUID` header, identifying this delivery in GitLab's logs
+    pub delivery_id: Option<String>,
+    /// Value of the `X-Gitlab-Event` header, e.g. `Pipeline Hook`
+    pub kind: String,
+    pub payload: GitlabPayload,
+    pub channel_id: ChannelId,
+    pub buttons: bool,
+    pub locale: Option<String>,
+    pub mirror: Option<MirrorSettings>,
+}
+
+impl<S> FromRequest<S> for GitlabEvent
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let headers = req.headers().clone();
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .context(InvalidBodySnafu)?;
+
+        let kind = headers
+            .get("X-Gitlab-Event")
+            .and_then(|header| header.to_str().ok())
+            .context(InvalidHeaderSnafu)?
+            .to_string();
+
+        // GitLab sends the secret as is rather than signing the body with it, so the body isn't
+        // looked at before the secret is known to be one of ours
+        let token = headers
+            .get("X-Gitlab-Token")
+            .map(|header| header.as_bytes())
+            .context(InvalidGitlabTokenSnafu)?;
+
+        let configs = Config::get()
+            .values()
+            .filter(|config| bool::from(token.ct_eq(config.webhook_secret.as_bytes())))
+            .cloned()
+            .collect::<Vec<Config>>();
+
+        if configs.is_empty() {
+            return Err(Error::InvalidGitlabToken);
+        }
+
+        let payload = serde_json::from_slice::<GitlabPayload>(&body)
+            .context(DeserializationErrorJsonSnafu)?;
+
+        // The secret has to belong to the project the event is about, events that are ignored
+        // anyway don't say which one that is
+        let config = match payload.project() {
+            Some(project) => configs
+                .into_iter()
+                .find(|config| config.matches_url(&project.web_url)),
+            None => configs.into_iter().next(),
+        }
+        .context(InvalidConfigSnafu)?;
+
+        let delivery_id = headers
+            .get("X-Gitlab-Event-UUID")
+            .and_then(|header| header.to_str().ok())
+            .map(String::from);
+
+        Ok(GitlabEvent {
+            delivery_id,
+            kind,
+            payload,
+            channel_id: config.channel_id,
+            buttons: config.buttons,
+            locale: config.locale,
+            mirror: config.mirror,
+        })
+    }
+}
@@ -0,0 +1,426 @@
+use crate::app::{App, Forge};
+use crate::builds::alerts::streak_summary;
+use crate::builds::changelog::format_commits;
+use crate::builds::commits::{CommitInfo, SKIP_PATTERNS};
+use crate::builds::conclusions::{run_status, should_delete};
+use crate::builds::messages::{
+    BuildChannel, BuildEmbed, BuildMessage, FinalMessage, announce_running, build_links,
+    button_row, finish_build, finished_status, running_status,
+};
+use crate::builds::timing::{Timing, estimated_completion, timing_summary, usual_run_time};
+use crate::config::repo_config::{RepoSettings, ThreadMode};
+use crate::error::Error;
+use crate::error::*;
+use crate::gitlab::client::{artifact_sources, raw_file, repo_config};
+use crate::gitlab::models::{GitlabPayload, PipelineEvent, PipelineJob, PipelineStatus, Project};
+use crate::gitlab::verification::GitlabEvent;
+use crate::history::store::{Build, BuildStatus};
+use crate::i18n::Locale;
+use crate::mirror::archive::{mirror_artifacts, mirror_variables};
+use crate::queue::worker::Delivery;
+use crate::util::time::format_duration;
+use axum::extract::State;
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use regex::Regex;
+use serenity::all::*;
+use snafu::{OptionExt, ResultExt};
+use tracing::log::error;
+
+/// Stands in for the workflow path, a project only has the one pipeline definition
+pub const PIPELINE_WORKFLOW: &str = ".gitlab-ci.yml";
+
+pub async fn handle_gitlab_webhook(
+    State(app): State<App>,
+    event: GitlabEvent,
+) -> Result<(StatusCode, &'static str), Error> {
+    // Nothing would be done with it, so it isn't worth a place in the queue
+    if matches!(event.payload, GitlabPayload::Ignored) {
+        return Ok((StatusCode::ACCEPTED, "Thanks and so long for all fish"));
+    }
+
+    if !app.queue.enqueue(Delivery::Gitlab(Box::new(event))) {
+        return Err(Error::QueueFull);
+    }
+
+    Ok((StatusCode::ACCEPTED, "Thanks and so long for all fish"))
+}
+
+/// Handles a verified webhook event, called by the queue's workers
+pub async fn process_event(app: App, event: GitlabEvent) -> Result<(), Error> {
+    match event.payload.clone() {
+        GitlabPayload::Push(payload) => {
+            let skipped = payload
+                .commits
+                .iter()
+                .find(|commit| commit.id == payload.after)
+                .is_some_and(|head| SKIP_PATTERNS.iter().any(|skip| head.message.contains(skip)));
+
+            if !skipped {
+                app.cache
+                    .commits
+                    .insert(
+                        payload.after,
+                        payload.commits.into_iter().map(Into::into).collect(),
+                    )
+                    .await
+            }
+        }
+        GitlabPayload::Pipeline(payload) => {
+            // Pending, manual and the like don't change the message
+            if payload.object_attributes.status == PipelineStatus::Waiting {
+                return Ok(());
+            }
+
+            let config =
+                repo_config(&app, &payload.project, &payload.object_attributes.sha).await?;
+
+            if config.workflows.iter().any(|w| w == PIPELINE_WORKFLOW) {
+                handle_pipeline(&app, &event, &config, *payload).await?;
+            }
+        }
+        GitlabPayload::Ignored => {}
+    }
+
+    Ok(())
+}
+
+async fn handle_pipeline(
+    app: &App,
+    event: &GitlabEvent,
+    config: &RepoSettings,
+    payload: PipelineEvent,
+) -> Result<(), Error> {
+    let pipeline = payload.object_attributes;
+    let project = payload.project;
+    let locale = Locale::resolve([config.locale.as_deref(), event.locale.as_deref()]);
+    let branch = pipeline.r#ref.clone();
+    let run_number = pipeline.number();
+    let run_url = pipeline.web_url(&project);
+
+    let regex = Regex::new(&config.mod_version.regex).context(InvalidRegexSnafu)?;
+
+    let mod_version = raw_file(app, &project, &config.mod_version.path, &pipeline.sha)
+        .await?
+        .and_then(|c| config.mod_version.capture(&regex, &c))
+        .context(FailedToUnwrapValueSnafu)?;
+
+    let formatted_mod_version =
+        config
+            .mod_version
+            .display(&mod_version, &config.minecraft_version, run_number);
+
+    let build_number = run_number.to_string();
+    let variables = [
+        ("${version}", formatted_mod_version.as_str()),
+        ("${mod_version}", mod_version.as_str()),
+        ("${minecraft_version}", config.minecraft_version.as_str()),
+        ("${build_number}", build_number.as_str()),
+    ];
+
+    // Commits recorded from push events are preferred, otherwise the pipeline's own commit is used
+    let commits = match app
+        .cache
+        .commits
+        .get(&pipeline.sha)
+        .await
+        .filter(|commits| !commits.is_empty())
+    {
+        Some(commits) => commits,
+        None => payload
+            .commit
+            .map(|commit| vec![CommitInfo::from(commit)])
+            .unwrap_or_default(),
+    };
+
+    let commits_url = format!(
+        "{}/-/commits/{}",
+        project.web_url.trim_end_matches('/'),
+        pipeline.sha
+    );
+    let commit_info = match format_commits(locale, &commits, config.changelog.as_ref()) {
+        formatted_commits if formatted_commits.is_empty() => locale.text("build.no_commits"),
+        formatted_commits => formatted_commits,
+    };
+
+    // Deliveries can arrive in any order, so handle one event per pipeline at a time
+    let _run_lock = app.lock_run((Forge::Gitlab, pipeline.id)).await;
+    let channel = BuildChannel {
+        forge: Forge::Gitlab,
+        config,
+        locale,
+        channel_id: event.channel_id,
+    };
+
+    let recorded = app
+        .history
+        .build(&project.path_with_namespace, pipeline.id)
+        .await?;
+
+    let (run_attempt, previous_message) = match recorded {
+        Some(recorded) if recorded.status == BuildStatus::Completed => {
+            // Retrying a job puts a finished pipeline back to running, but only a job that started
+            // after the pipeline finished tells a retry apart from a late or duplicate delivery
+            let retried = pipeline.status == PipelineStatus::Running
+                && recorded.completed_at.is_some_and(|completed_at| {
+                    payload
+                        .builds
+                        .iter()
+                        .filter_map(|job| job.started_at)
+                        .any(|started_at| started_at > completed_at)
+                });
+
+            if !retried {
+                return Ok(());
+            }
+
+            (recorded.run_attempt + 1, recorded.message_id)
+        }
+        Some(recorded) => {
+            // Already announced as running
+            if pipeline.status == PipelineStatus::Running {
+                return Ok(());
+            }
+
+            (recorded.run_attempt, recorded.message_id)
+        }
+        None => (1, None),
+    };
+    let previous_message = previous_message.map(MessageId::new);
+
+    let build = Build {
+        repository: project.path_with_namespace.clone(),
+        branch: branch.clone(),
+        workflow: PIPELINE_WORKFLOW.into(),
+        run_id: pipeline.id,
+        run_attempt,
+        run_number,
+        head_sha: pipeline.sha.clone(),
+        version: formatted_mod_version.clone(),
+        status: BuildStatus::InProgress,
+        conclusion: None,
+        started_at: pipeline.created_at,
+        completed_at: None,
+        duration: None,
+        commits,
+        url: run_url.clone(),
+        message_link: None,
+        links: Vec::new(),
+        message_id: None,
+        queued: None,
+        private: !project.is_public(),
+    };
+
+    let mut author = CreateEmbedAuthor::new(format!("{}/{}", project.name, branch)).url(&run_url);
+
+    if let Some(avatar_url) = &project.avatar_url {
+        author = author.icon_url(avatar_url);
+    }
+
+    let footer = payload.user.as_ref().map(|user| {
+        let footer = CreateEmbedFooter::new(&user.username);
+
+        match &user.avatar_url {
+            Some(avatar_url) => footer.icon_url(avatar_url),
+            None => footer,
+        }
+    });
+
+    let build_embed = BuildEmbed {
+        locale,
+        author,
+        footer,
+        started_at: pipeline.created_at,
+        version: formatted_mod_version.clone(),
+        commits: commit_info,
+        commits_link: Some(locale.format("build.too_many_commits", &[("url", &commits_url)])),
+    };
+
+    let queued = pipeline
+        .queued_duration
+        .map(|queued| queued as i64)
+        .unwrap_or_default();
+
+    let Some(conclusion) = pipeline.status.conclusion() else {
+        let status = running_status(app, locale, run_attempt, run_number).await?;
+        let estimate = usual_run_time(app, &build)
+            .await
+            .map(|usual| {
+                let started_at = pipeline.created_at + Duration::seconds(queued);
+                let eta = estimated_completion(locale, started_at, usual);
+                format!("-# {eta}\n")
+            })
+            .unwrap_or_default();
+
+        announce_running(
+            app,
+            channel,
+            previous_message,
+            build_embed.embed(&status, &estimate),
+            build,
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    // Queue time says more about the runners than the build, so it's kept out of the duration
+    // shown and compared, same as for workflow runs
+    let finished_at = pipeline.finished_at.unwrap_or_else(Utc::now);
+    let total = (finished_at - pipeline.created_at).num_seconds().max(0);
+    let timing = Timing {
+        queued: queued.clamp(0, total),
+        run: total - queued.clamp(0, total),
+    };
+
+    let build = Build {
+        status: BuildStatus::Completed,
+        conclusion: serde_json::to_value(conclusion)
+            .ok()
+            .and_then(|c| c.as_str().map(String::from)),
+        completed_at: Some(finished_at),
+        duration: Some(total),
+        queued: Some(timing.queued),
+        ..build
+    };
+
+    let conclusion_settings = config.conclusions.get(&conclusion);
+
+    if should_delete(app, &build, conclusion_settings).await {
+        if let Some(message_id) = previous_message
+            && let Err(e) = event
+                .channel_id
+                .delete_message(&app.serenity_http, message_id)
+                .await
+        {
+            error!("Failed to delete build message: {e}");
+        }
+
+        if let Err(e) = app.history.record(build).await {
+            error!("Failed to record build: {e}");
+        }
+
+        return Ok(());
+    }
+
+    let mut components = None;
+    let mut links = Vec::new();
+    let mut logs = None;
+    let mut usual = None;
+
+    match pipeline.status {
+        PipelineStatus::Success => {
+            let mirrored = mirror_variables(event.mirror.as_ref(), config, &run_url, &build);
+            let variables = variables
+                .into_iter()
+                .chain(
+                    mirrored
+                        .iter()
+                        .map(|(placeholder, url)| (placeholder.as_str(), url.as_str())),
+                )
+                .collect::<Vec<(&str, &str)>>();
+
+            if event.buttons {
+                components = Some(vec![button_row(config, &variables)]);
+            }
+
+            links = build_links(config, &variables);
+            usual = usual_run_time(app, &build).await;
+        }
+        PipelineStatus::Failed => {
+            let diagnostics = failed_jobs(locale, &project, &payload.builds)
+                .unwrap_or_else(|| locale.format("build.run_logs", &[("url", &run_url)]));
+
+            logs = Some(format!("{diagnostics}\n"));
+        }
+        _ => {}
+    }
+
+    let (run_status, color) =
+        run_status(app, locale, Some(conclusion), conclusion_settings).await?;
+    let status = finished_status(locale, &run_status, run_attempt, run_number, timing.run);
+
+    let streak = app
+        .history
+        .failing_streak(
+            &build.repository,
+            &build.branch,
+            &build.workflow,
+            build.run_id,
+        )
+        .await
+        .inspect_err(|e| error!("Failed to find failing streak: {e}"))
+        .unwrap_or_default();
+
+    let previous = app
+        .history
+        .previous_build(
+            &build.repository,
+            &build.branch,
+            &build.workflow,
+            build.run_id,
+        )
+        .await
+        .inspect_err(|e| error!("Failed to find previous build: {e}"))
+        .ok()
+        .flatten();
+
+    let extra = streak_summary(locale, &build, &streak, previous.as_ref())
+        .map(|summary| format!("-# {summary}\n"))
+        .unwrap_or_default()
+        + &timing_summary(locale, timing, usual)
+            .map(|summary| format!("-# {summary}\n"))
+            .unwrap_or_default()
+        + &logs.unwrap_or_default();
+
+    let failed = pipeline.status == PipelineStatus::Failed;
+    let message = FinalMessage {
+        message_id: previous_message,
+        message: BuildMessage {
+            embed: build_embed.embed(&status, &extra).color(color),
+            components: components.unwrap_or_default(),
+            attachments: Vec::new(),
+        },
+        status,
+        threads: config
+            .threads
+            .as_ref()
+            .filter(|threads| threads.mode == ThreadMode::All || failed),
+        follow_up: None,
+    };
+    let build = Build { links, ..build };
+
+    let (build, _) = finish_build(app, channel, message, build, previous.as_ref()).await?;
+
+    if pipeline.status == PipelineStatus::Success
+        && let Some(mirror) = &event.mirror
+    {
+        let artifacts = artifact_sources(app, &project, &payload.builds);
+        mirror_artifacts(app, mirror, &build, artifacts).await;
+    }
+
+    Ok(())
+}
+
+/// Lists the jobs that failed the pipeline, linking to each of their logs
+fn failed_jobs(locale: Locale, project: &Project, jobs: &[PipelineJob]) -> Option<String> {
+    let lines = jobs
+        .iter()
+        .filter(|job| job.status == PipelineStatus::Failed && !job.allow_failure)
+        .map(|job| {
+            let duration = job
+                .started_at
+                .zip(job.finished_at)
+                .map(|(started_at, finished_at)| {
+                    let duration =
+                        format_duration(locale, (finished_at - started_at).num_seconds());
+                    locale.format("build.in", &[("duration", &duration)])
+                })
+                .unwrap_or_default();
+
+            format!("❌ [{}]({}){duration}", job.name, job.web_url(project))
+        })
+        .collect::<Vec<String>>();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
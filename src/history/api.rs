@@ -0,0 +1,55 @@
+use crate::app::App;
+use crate::config::app_config::Config;
+use crate::error::Error;
+use crate::error::*;
+use crate::site::visibility::ensure_public;
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use snafu::OptionExt;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+
+#[derive(Deserialize)]
+pub struct BuildsQuery {
+    pub branch: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Serves `/api/repos/{repository}/builds` and `/api/repos/{repository}/builds/{run_id}`, the
+/// repository being whichever configured one the path starts with
+pub async fn builds(
+    State(app): State<App>,
+    Path(path): Path<String>,
+    Query(query): Query<BuildsQuery>,
+) -> Result<Response, Error> {
+    let (config, rest) = Config::find_prefix(&path).context(RepositoryNotFoundSnafu)?;
+    ensure_public(&app, &config).await?;
+    let repository = config.full_name();
+
+    if rest == "builds" {
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let builds = app
+            .history
+            .builds(repository, query.branch.as_deref(), limit)
+            .await?;
+
+        return Ok(Json(builds).into_response());
+    }
+
+    let run_id = rest
+        .strip_prefix("builds/")
+        .and_then(|run_id| run_id.parse::<u64>().ok())
+        .context(BuildNotFoundSnafu)?;
+
+    let build = app
+        .history
+        .build(repository, run_id)
+        .await?
+        .context(BuildNotFoundSnafu)?;
+
+    Ok(Json(build).into_response())
+}
@@ -0,0 +1,652 @@
+use crate::builds::commits::CommitInfo;
+use crate::error::Error;
+use crate::error::*;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+/// Applied in order, the database's `user_version` tracks how many have already run
+const MIGRATIONS: &[&str] = &[
+    r"
+    CREATE TABLE IF NOT EXISTS builds (
+        repository TEXT NOT NULL COLLATE NOCASE,
+        branch TEXT NOT NULL,
+        workflow TEXT NOT NULL,
+        run_id INTEGER NOT NULL,
+        run_attempt INTEGER NOT NULL,
+        run_number INTEGER NOT NULL,
+        head_sha TEXT NOT NULL,
+        version TEXT NOT NULL,
+        status TEXT NOT NULL,
+        conclusion TEXT,
+        started_at INTEGER NOT NULL,
+        completed_at INTEGER,
+        duration INTEGER,
+        commits TEXT NOT NULL,
+        url TEXT NOT NULL,
+        message_link TEXT,
+        PRIMARY KEY (repository, run_id, run_attempt)
+    );
+
+    CREATE INDEX IF NOT EXISTS builds_by_branch ON builds (repository, branch, started_at DESC);
+    ",
+    r"
+    ALTER TABLE builds ADD COLUMN links TEXT NOT NULL DEFAULT '[]';
+    ",
+    r"
+    ALTER TABLE builds ADD COLUMN message_id INTEGER;
+    ",
+    r"
+    ALTER TABLE builds ADD COLUMN queued INTEGER;
+    ",
+    r"
+    CREATE TABLE IF NOT EXISTS mirrored_artifacts (
+        repository TEXT NOT NULL COLLATE NOCASE,
+        branch TEXT NOT NULL,
+        run_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        sha256 TEXT NOT NULL,
+        mirrored_at INTEGER NOT NULL,
+        PRIMARY KEY (repository, run_id, name)
+    );
+
+    CREATE INDEX IF NOT EXISTS mirrored_artifacts_by_branch
+        ON mirrored_artifacts (repository, branch, mirrored_at DESC);
+    ",
+    r"
+    ALTER TABLE builds ADD COLUMN private INTEGER NOT NULL DEFAULT 0;
+    ",
+];
+
+const COLUMNS: &str = "repository, branch, workflow, run_id, run_attempt, run_number, head_sha, \
+    version, status, conclusion, started_at, completed_at, duration, commits, url, message_link, \
+    links, message_id, queued, private";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Build {
+    /// Full name of the repository, e.g. `owner/repo`
+    pub repository: String,
+    pub branch: String,
+    /// Path of the workflow file that produced the build
+    pub workflow: String,
+    pub run_id: u64,
+    pub run_attempt: i64,
+    pub run_number: i64,
+    pub head_sha: String,
+    pub version: String,
+    pub status: BuildStatus,
+    pub conclusion: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Duration of the build in seconds, including the time it was queued for
+    pub duration: Option<i64>,
+    /// Seconds the build waited for a runner before its first job started
+    pub queued: Option<i64>,
+    pub commits: Vec<CommitInfo>,
+    pub url: String,
+    pub message_link: Option<String>,
+    /// Download links resolved from the repository's link buttons
+    pub links: Vec<BuildLink>,
+    /// Discord message the build was announced in
+    pub message_id: Option<u64>,
+    /// Whether the repository was private when the build ran
+    pub private: bool,
+}
+
+/// Copy of a build's artifact kept in the mirror, after GitHub has let it expire
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MirroredArtifact {
+    /// Full name of the repository, e.g. `owner/repo`
+    pub repository: String,
+    pub branch: String,
+    pub run_id: u64,
+    /// Name of the artifact on GitHub
+    pub name: String,
+    /// Size of the archive in bytes
+    pub size: u64,
+    /// Hex encoded SHA-256 checksum of the archive
+    pub sha256: String,
+    pub mirrored_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuildLink {
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildStatus {
+    InProgress,
+    Completed,
+}
+
+impl BuildStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BuildStatus::InProgress => "in_progress",
+            BuildStatus::Completed => "completed",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BuildHistory {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl BuildHistory {
+    pub fn open() -> rusqlite::Result<Self> {
+        let path = env::var("DATABASE_PATH").unwrap_or_else(|_| "./history.db".into());
+
+        Self::open_at(&path)
+    }
+
+    /// Opens the database at the path, `:memory:` keeps it in memory
+    pub fn open_at(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+
+        let version: usize = connection.pragma_query_value(None, "user_version", |r| r.get(0))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            connection.execute_batch(migration)?;
+            connection.pragma_update(None, "user_version", index + 1)?;
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn with_connection<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+            f(&connection)
+        })
+        .await
+        .context(TaskJoinSnafu)?
+        .context(DatabaseSnafu)
+    }
+
+    /// Inserts the build, replacing any previous record of the same run attempt
+    pub async fn record(&self, build: Build) -> Result<(), Error> {
+        let commits = serde_json::to_string(&build.commits).context(SerializationErrorJsonSnafu)?;
+        let links = serde_json::to_string(&build.links).context(SerializationErrorJsonSnafu)?;
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO builds ({COLUMNS}) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)"
+                ),
+                params![
+                    build.repository,
+                    build.branch,
+                    build.workflow,
+                    build.run_id,
+                    build.run_attempt,
+                    build.run_number,
+                    build.head_sha,
+                    build.version,
+                    build.status.as_str(),
+                    build.conclusion,
+                    build.started_at.timestamp(),
+                    build.completed_at.map(|t| t.timestamp()),
+                    build.duration,
+                    commits,
+                    build.url,
+                    build.message_link,
+                    links,
+                    build.message_id,
+                    build.queued,
+                    build.private,
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Most recent builds of a repository, newest first
+    pub async fn builds(
+        &self,
+        repository: &str,
+        branch: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.map(str::to_string);
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM builds \
+                 WHERE repository = ?1 AND (?2 IS NULL OR branch = ?2) \
+                 ORDER BY started_at DESC, run_attempt DESC LIMIT ?3"
+            ))?;
+
+            statement
+                .query_map(params![repository, branch, limit], Build::from_row)?
+                .collect()
+        })
+        .await
+    }
+
+    /// Most recent successful builds of a repository, newest first
+    pub async fn successful_builds(
+        &self,
+        repository: &str,
+        branch: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.map(str::to_string);
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM builds \
+                 WHERE repository = ?1 AND (?2 IS NULL OR branch = ?2) \
+                 AND status = 'completed' AND conclusion = 'success' \
+                 ORDER BY completed_at DESC LIMIT ?3"
+            ))?;
+
+            statement
+                .query_map(params![repository, branch, limit], Build::from_row)?
+                .collect()
+        })
+        .await
+    }
+
+    /// Latest build of every branch of a repository, most recently started first
+    pub async fn latest_builds(&self, repository: &str) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM ( \
+                     SELECT *, ROW_NUMBER() OVER ( \
+                         PARTITION BY branch ORDER BY started_at DESC, run_attempt DESC \
+                     ) AS position FROM builds WHERE repository = ?1 \
+                 ) WHERE position = 1 ORDER BY started_at DESC"
+            ))?;
+
+            statement
+                .query_map(params![repository], Build::from_row)?
+                .collect()
+        })
+        .await
+    }
+
+    /// Whether the repository was private as of its latest build, `false` when it has none
+    pub async fn is_private(&self, repository: &str) -> Result<bool, Error> {
+        let repository = repository.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    "SELECT private FROM builds WHERE repository = ?1 \
+                     ORDER BY started_at DESC, run_attempt DESC LIMIT 1",
+                    params![repository],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map(Option::unwrap_or_default)
+        })
+        .await
+    }
+
+    /// Latest attempt of every workflow that ran for a commit
+    pub async fn commit_builds(
+        &self,
+        repository: &str,
+        head_sha: &str,
+    ) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+        let head_sha = head_sha.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM ( \
+                     SELECT *, ROW_NUMBER() OVER ( \
+                         PARTITION BY workflow ORDER BY started_at DESC, run_attempt DESC \
+                     ) AS position FROM builds WHERE repository = ?1 AND head_sha = ?2 \
+                 ) WHERE position = 1"
+            ))?;
+
+            statement
+                .query_map(params![repository, head_sha], Build::from_row)?
+                .collect()
+        })
+        .await
+    }
+
+    /// Latest build of a branch other than the given run, whichever workflow it came from
+    pub async fn last_branch_build(
+        &self,
+        repository: &str,
+        branch: &str,
+        run_id: u64,
+    ) -> Result<Option<Build>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!(
+                        "SELECT {COLUMNS} FROM builds \
+                         WHERE repository = ?1 AND branch = ?2 AND run_id != ?3 \
+                         ORDER BY started_at DESC, run_attempt DESC LIMIT 1"
+                    ),
+                    params![repository, branch, run_id],
+                    Build::from_row,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    /// Latest completed build of a branch's workflow other than the given run
+    pub async fn previous_build(
+        &self,
+        repository: &str,
+        branch: &str,
+        workflow: &str,
+        run_id: u64,
+    ) -> Result<Option<Build>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.to_string();
+        let workflow = workflow.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!(
+                        "SELECT {COLUMNS} FROM builds \
+                         WHERE repository = ?1 AND branch = ?2 AND workflow = ?3 AND run_id != ?4 \
+                         AND status = 'completed' \
+                         ORDER BY started_at DESC, run_attempt DESC LIMIT 1"
+                    ),
+                    params![repository, branch, workflow, run_id],
+                    Build::from_row,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    /// Failed builds of a branch's workflow since it last succeeded, oldest first, leaving out
+    /// the given run
+    pub async fn failing_streak(
+        &self,
+        repository: &str,
+        branch: &str,
+        workflow: &str,
+        run_id: u64,
+    ) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.to_string();
+        let workflow = workflow.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM ( \
+                     SELECT *, ROW_NUMBER() OVER ( \
+                         PARTITION BY run_id ORDER BY run_attempt DESC \
+                     ) AS position FROM builds \
+                     WHERE repository = ?1 AND branch = ?2 AND workflow = ?3 AND run_id != ?4 \
+                     AND status = 'completed' \
+                 ) WHERE position = 1 AND conclusion IN ('failure', 'timed_out') \
+                 AND started_at > COALESCE(( \
+                     SELECT MAX(started_at) FROM builds \
+                     WHERE repository = ?1 AND branch = ?2 AND workflow = ?3 AND run_id != ?4 \
+                     AND conclusion = 'success' \
+                 ), 0) \
+                 ORDER BY started_at ASC"
+            ))?;
+
+            statement
+                .query_map(
+                    params![repository, branch, workflow, run_id],
+                    Build::from_row,
+                )?
+                .collect()
+        })
+        .await
+    }
+
+    /// Run times in seconds of a branch's latest successful builds of a workflow, newest first,
+    /// leaving out the given run
+    pub async fn run_times(
+        &self,
+        repository: &str,
+        branch: &str,
+        workflow: &str,
+        run_id: u64,
+        limit: u32,
+    ) -> Result<Vec<i64>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.to_string();
+        let workflow = workflow.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT duration - COALESCE(queued, 0) FROM builds \
+                 WHERE repository = ?1 AND branch = ?2 AND workflow = ?3 AND run_id != ?4 \
+                 AND conclusion = 'success' AND duration IS NOT NULL \
+                 ORDER BY started_at DESC LIMIT ?5",
+            )?;
+
+            statement
+                .query_map(
+                    params![repository, branch, workflow, run_id, limit],
+                    |row| row.get(0),
+                )?
+                .collect()
+        })
+        .await
+    }
+
+    /// Inserts the mirrored artifact, replacing any earlier copy of it
+    pub async fn record_mirrored(&self, artifact: MirroredArtifact) -> Result<(), Error> {
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO mirrored_artifacts \
+                 (repository, branch, run_id, name, size, sha256, mirrored_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    artifact.repository,
+                    artifact.branch,
+                    artifact.run_id,
+                    artifact.name,
+                    artifact.size,
+                    artifact.sha256,
+                    artifact.mirrored_at.timestamp(),
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn mirrored_artifact(
+        &self,
+        repository: &str,
+        run_id: u64,
+        name: &str,
+    ) -> Result<Option<MirroredArtifact>, Error> {
+        let repository = repository.to_string();
+        let name = name.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    "SELECT * FROM mirrored_artifacts \
+                     WHERE repository = ?1 AND run_id = ?2 AND name = ?3",
+                    params![repository, run_id, name],
+                    MirroredArtifact::from_row,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    /// Mirrored artifacts of a branch outside the retention policy, those of builds older than
+    /// the cutoff or beyond the newest `keep` builds
+    pub async fn prunable_mirrors(
+        &self,
+        repository: &str,
+        branch: &str,
+        keep: Option<u32>,
+        cutoff: Option<DateTime<Utc>>,
+    ) -> Result<Vec<MirroredArtifact>, Error> {
+        let repository = repository.to_string();
+        let branch = branch.to_string();
+        // SQLite reads a negative limit as no limit at all
+        let keep = keep.map(i64::from).unwrap_or(-1);
+        let cutoff = cutoff.map(|cutoff| cutoff.timestamp()).unwrap_or(i64::MIN);
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT * FROM mirrored_artifacts \
+                 WHERE repository = ?1 AND branch = ?2 AND (mirrored_at < ?3 OR run_id NOT IN ( \
+                     SELECT run_id FROM mirrored_artifacts \
+                     WHERE repository = ?1 AND branch = ?2 \
+                     GROUP BY run_id ORDER BY MAX(mirrored_at) DESC LIMIT ?4 \
+                 ))",
+            )?;
+
+            statement
+                .query_map(
+                    params![repository, branch, cutoff, keep],
+                    MirroredArtifact::from_row,
+                )?
+                .collect()
+        })
+        .await
+    }
+
+    pub async fn remove_mirrored(&self, artifact: &MirroredArtifact) -> Result<(), Error> {
+        let repository = artifact.repository.clone();
+        let run_id = artifact.run_id;
+        let name = artifact.name.clone();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM mirrored_artifacts \
+                 WHERE repository = ?1 AND run_id = ?2 AND name = ?3",
+                params![repository, run_id, name],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Every recorded attempt of a workflow run, oldest first
+    pub async fn attempts(&self, repository: &str, run_id: u64) -> Result<Vec<Build>, Error> {
+        let repository = repository.to_string();
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM builds WHERE repository = ?1 AND run_id = ?2 \
+                 ORDER BY run_attempt ASC"
+            ))?;
+
+            statement
+                .query_map(params![repository, run_id], Build::from_row)?
+                .collect()
+        })
+        .await
+    }
+
+    /// Latest attempt of a workflow run
+    pub async fn build(&self, repository: &str, run_id: u64) -> Result<Option<Build>, Error> {
+        let repository = repository.to_string();
+
+        self.with_connection(move |connection| {
+            connection
+                .query_row(
+                    &format!(
+                        "SELECT {COLUMNS} FROM builds WHERE repository = ?1 AND run_id = ?2 \
+                         ORDER BY run_attempt DESC LIMIT 1"
+                    ),
+                    params![repository, run_id],
+                    Build::from_row,
+                )
+                .optional()
+        })
+        .await
+    }
+}
+
+impl Build {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let timestamp = |timestamp: i64| DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        let status = match row.get::<_, String>("status")?.as_str() {
+            "completed" => BuildStatus::Completed,
+            _ => BuildStatus::InProgress,
+        };
+        let commits = serde_json::from_str(&row.get::<_, String>("commits")?).unwrap_or_default();
+        let links = serde_json::from_str(&row.get::<_, String>("links")?).unwrap_or_default();
+
+        Ok(Self {
+            repository: row.get("repository")?,
+            branch: row.get("branch")?,
+            workflow: row.get("workflow")?,
+            run_id: row.get("run_id")?,
+            run_attempt: row.get("run_attempt")?,
+            run_number: row.get("run_number")?,
+            head_sha: row.get("head_sha")?,
+            version: row.get("version")?,
+            status,
+            conclusion: row.get("conclusion")?,
+            started_at: timestamp(row.get("started_at")?),
+            completed_at: row.get::<_, Option<i64>>("completed_at")?.map(timestamp),
+            duration: row.get("duration")?,
+            commits,
+            url: row.get("url")?,
+            message_link: row.get("message_link")?,
+            links,
+            message_id: row.get("message_id")?,
+            queued: row.get("queued")?,
+            private: row.get("private")?,
+        })
+    }
+}
+
+impl MirroredArtifact {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            repository: row.get("repository")?,
+            branch: row.get("branch")?,
+            run_id: row.get("run_id")?,
+            name: row.get("name")?,
+            size: row.get("size")?,
+            sha256: row.get("sha256")?,
+            mirrored_at: DateTime::from_timestamp(row.get("mirrored_at")?, 0).unwrap_or_default(),
+        })
+    }
+
+    /// Where the archive is kept in the mirror's storage
+    pub fn key(&self) -> String {
+        Self::key_of(&self.repository, self.run_id, &self.name)
+    }
+
+    /// Where the archive of a run's artifact is kept, whether or not it has been mirrored yet
+    pub fn key_of(repository: &str, run_id: u64, name: &str) -> String {
+        format!("{repository}/{run_id}/{name}.zip")
+    }
+}
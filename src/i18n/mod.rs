@@ -0,0 +1,194 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use tracing::log::error;
+
+const FALLBACK: &str = "en";
+
+/// Translation catalogs compiled into the binary, keyed by language code
+const CATALOGS: &[(&str, &str)] = &[
+    ("de", include_str!("locales/de.yml")),
+    ("en", include_str!("locales/en.yml")),
+    ("es", include_str!("locales/es.yml")),
+    ("fr", include_str!("locales/fr.yml")),
+    ("pl", include_str!("locales/pl.yml")),
+];
+
+static TRANSLATIONS: Lazy<HashMap<&'static str, HashMap<String, Entry>>> = Lazy::new(|| {
+    CATALOGS
+        .iter()
+        .filter_map(|(language, catalog)| {
+            serde_norway::from_str(catalog)
+                .inspect_err(|e| error!("Invalid translation catalog {language}: {e}"))
+                .ok()
+                .map(|entries| (*language, entries))
+        })
+        .collect()
+});
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    /// Forms keyed by plural category, see [`Locale::plural_category`]
+    Plural(HashMap<String, String>),
+}
+
+/// Language user-facing text is shown in, English when nothing else is configured
+#[derive(Clone, Copy, Debug)]
+pub struct Locale {
+    language: &'static str,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self { language: FALLBACK }
+    }
+}
+
+impl Locale {
+    /// Picks the first locale with a catalog, accepting region tags like `de-DE`
+    pub fn resolve<'a>(candidates: impl IntoIterator<Item = Option<&'a str>>) -> Self {
+        candidates
+            .into_iter()
+            .flatten()
+            .find_map(|code| {
+                let language = code.split(['-', '_']).next()?.to_lowercase();
+
+                CATALOGS
+                    .iter()
+                    .find(|(catalog, _)| *catalog == language)
+                    .map(|(language, _)| Self { language })
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn text(self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// Translates the key, filling in `{name}` placeholders
+    pub fn format(self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let text = match self.entry(key) {
+            Some(Entry::Text(text)) => text.as_str(),
+            Some(Entry::Plural(forms)) => forms
+                .get("other")
+                .or_else(|| forms.get("many"))
+                .map(String::as_str)
+                .unwrap_or(key),
+            None => key,
+        };
+
+        fill(text, args)
+    }
+
+    /// Translates the key using the plural form that fits the count, which is available as
+    /// `{count}`
+    pub fn plural(self, key: &str, count: i64, args: &[(&str, &dyn Display)]) -> String {
+        let category = self.plural_category(count);
+
+        let text = match self.entry(key) {
+            Some(Entry::Plural(forms)) => forms
+                .get(category)
+                .or_else(|| forms.get("other"))
+                .or_else(|| forms.get("many"))
+                .map(String::as_str)
+                .unwrap_or(key),
+            Some(Entry::Text(text)) => text.as_str(),
+            None => key,
+        };
+
+        let mut args = args.to_vec();
+        args.push(("count", &count));
+
+        fill(text, &args)
+    }
+
+    fn entry(self, key: &str) -> Option<&'static Entry> {
+        TRANSLATIONS
+            .get(self.language)
+            .and_then(|entries| entries.get(key))
+            .or_else(|| TRANSLATIONS.get(FALLBACK)?.get(key))
+    }
+
+    /// CLDR plural category of the count for the languages we have catalogs for
+    fn plural_category(self, count: i64) -> &'static str {
+        let count = count.unsigned_abs();
+
+        match self.language {
+            "fr" if count <= 1 => "one",
+            "pl" if count == 1 => "one",
+            "pl" if (2..=4).contains(&(count % 10)) && !(12..=14).contains(&(count % 100)) => "few",
+            "pl" => "many",
+            _ if count == 1 => "one",
+            _ => "other",
+        }
+    }
+}
+
+fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), &value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(language: &'static str, counts: &[i64]) -> Vec<&'static str> {
+        let locale = Locale { language };
+
+        counts
+            .iter()
+            .map(|count| locale.plural_category(*count))
+            .collect()
+    }
+
+    #[test]
+    fn english_has_one_and_other() {
+        assert_eq!(
+            categories("en", &[0, 1, 2, 11, 21, -1]),
+            ["other", "one", "other", "other", "other", "one"]
+        );
+    }
+
+    #[test]
+    fn french_counts_zero_as_one() {
+        assert_eq!(categories("fr", &[0, 1, 2]), ["one", "one", "other"]);
+    }
+
+    #[test]
+    fn polish_has_few_and_many() {
+        assert_eq!(
+            categories("pl", &[1, 2, 4, 5, 11, 12, 14, 22, 25, 104, 112, 0]),
+            [
+                "one", "few", "few", "many", "many", "many", "many", "few", "many", "few", "many",
+                "many"
+            ]
+        );
+    }
+
+    #[test]
+    fn every_polish_plural_has_each_form() {
+        let Some(catalog) = TRANSLATIONS.get("pl") else {
+            panic!("The Polish catalog doesn't parse");
+        };
+
+        for (key, entry) in catalog {
+            if let Entry::Plural(forms) = entry {
+                for category in ["one", "few", "many", "other"] {
+                    assert!(forms.contains_key(category), "{key} has no {category} form");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_region_tags_and_falls_back_to_english() {
+        assert_eq!(Locale::resolve([Some("de-DE")]).language, "de");
+        assert_eq!(Locale::resolve([None, Some("pl_PL")]).language, "pl");
+        assert_eq!(Locale::resolve([Some("xx"), None]).language, FALLBACK);
+    }
+}
@@ -1,13 +1,28 @@
 mod app;
 mod bot;
+mod builds;
 mod config;
+mod downloads;
 mod error;
 mod github;
+mod gitlab;
+mod history;
+mod i18n;
+mod mirror;
+mod queue;
+mod site;
 mod util;
 
 use crate::app::App;
 use crate::config::app_config::Config;
+use crate::downloads::api::download;
 use crate::github::web::handle_github_webhhook;
+use crate::gitlab::web::handle_gitlab_webhook;
+use crate::history::api::builds;
+use crate::history::store::BuildHistory;
+use crate::mirror::api::mirrored_artifact;
+use crate::queue::api::{clear_dead_letters, list_dead_letters};
+use crate::site::{badges, dashboard, feeds};
 use axum::Router;
 use axum::http::StatusCode;
 use axum::routing::{get, post};
@@ -25,13 +40,12 @@ async fn main() -> Result<(), Whatever> {
         .init();
 
     // Use an authenticated instance if a token is passed
-    if let Ok(token) = env::var("GITHUB_TOKEN") {
-        if let Ok(instance) = octocrab::OctocrabBuilder::new()
+    if let Ok(token) = env::var("GITHUB_TOKEN")
+        && let Ok(instance) = octocrab::OctocrabBuilder::new()
             .personal_token(token)
             .build()
-        {
-            octocrab::initialise(instance);
-        }
+    {
+        octocrab::initialise(instance);
     }
 
     // Start the bot client
@@ -40,10 +54,28 @@ async fn main() -> Result<(), Whatever> {
     // We need to hang onto this for the watcher to actually... watch
     let _ = Config::watch();
 
+    let history = BuildHistory::open().whatever_context("Failed to open build history")?;
+
+    let app = App::new(bot_http, history);
+    app.queue.start(app.clone());
+
     let router = Router::new()
+        .route("/", get(dashboard::index))
         .route("/status", get(|| async { StatusCode::OK }))
         .route("/github/webhook", post(handle_github_webhhook))
-        .with_state(App::new(bot_http));
+        .route("/gitlab/webhook", post(handle_gitlab_webhook))
+        // Project paths and artifact names can contain slashes, so they're matched as wildcards
+        .route("/api/repos/{*path}", get(builds))
+        .route("/badge/{*path}", get(badges::badge))
+        .route("/feeds/{*feed}", get(feeds::feed))
+        .route("/repos/{*repository}", get(dashboard::repository))
+        .route("/download/{token}", get(download))
+        .route("/mirror/{*file}", get(mirrored_artifact))
+        .route(
+            "/admin/dead-letters",
+            get(list_dead_letters).delete(clear_dead_letters),
+        )
+        .with_state(app);
 
     let ip = env::var("APP_IP").unwrap_or("0.0.0.0".to_string());
     let port = env::var("APP_PORT").unwrap_or("3000".to_string());
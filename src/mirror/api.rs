@@ -0,0 +1,94 @@
+use crate::app::App;
+use crate::downloads::api::content_disposition;
+use crate::downloads::token::verify_signature;
+use crate::error::Error;
+use crate::error::*;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderName, header};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use snafu::OptionExt;
+
+const CHECKSUM_HEADER: HeaderName = HeaderName::from_static("x-checksum-sha256");
+
+#[derive(Deserialize)]
+pub struct MirrorQuery {
+    /// Signature of the artifact's key, so only links the bot handed out work
+    token: String,
+}
+
+/// Serves a mirrored artifact by its key, or its checksum when `.sha256` is appended
+pub async fn mirrored_artifact(
+    State(app): State<App>,
+    Path(file): Path<String>,
+    Query(query): Query<MirrorQuery>,
+) -> Result<Response, Error> {
+    let (key, checksum) = match file.strip_suffix(".sha256") {
+        Some(key) => (key, true),
+        None => (file.as_str(), false),
+    };
+
+    // Artifacts of private repositories are mirrored too, so only links handed out in their build
+    // messages work, or on public pages when the repository's config publishes it
+    if !verify_signature(key, &query.token) {
+        return Err(Error::InvalidDownloadToken);
+    }
+
+    let mut artifact = None;
+    for (repository, run_id, name) in key_parts(key) {
+        artifact = app
+            .history
+            .mirrored_artifact(repository, run_id, name)
+            .await?;
+
+        if artifact.is_some() {
+            break;
+        }
+    }
+    let artifact = artifact.context(MirrorNotFoundSnafu)?;
+
+    if checksum {
+        return Ok((
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            format!("{}  {}.zip\n", artifact.sha256, artifact.name),
+        )
+            .into_response());
+    }
+
+    let body = app
+        .mirror
+        .get(&artifact.key())
+        .await?
+        .context(MirrorNotFoundSnafu)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&format!("{}.zip", artifact.name)),
+            ),
+            (header::CONTENT_LENGTH, artifact.size.to_string()),
+            (CHECKSUM_HEADER, artifact.sha256),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Every way the key can split into the repository, run id and name it was made of. Both the
+/// repository and the name can contain slashes, so any numeric segment between them may be the run.
+fn key_parts(key: &str) -> Vec<(&str, u64, &str)> {
+    let Some(key) = key.strip_suffix(".zip") else {
+        return Vec::new();
+    };
+    let slashes = key.match_indices('/').map(|(i, _)| i).collect::<Vec<_>>();
+
+    slashes
+        .windows(2)
+        .filter_map(|pair| {
+            let run_id = key[pair[0] + 1..pair[1]].parse().ok()?;
+            Some((&key[..pair[0]], run_id, &key[pair[1] + 1..]))
+        })
+        .collect()
+}
@@ -0,0 +1,400 @@
+use crate::app::App;
+use crate::config::app_config::MirrorSettings;
+use crate::config::repo_config::RepoSettings;
+use crate::downloads::token::sign;
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::{Build, BuildHistory, MirroredArtifact};
+use crate::mirror::storage::{MirrorStorage, uri_encode};
+use chrono::{Duration, Utc};
+use hmac_sha256::Hash;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::RequestBuilder;
+use snafu::ResultExt;
+use std::env;
+use std::num::NonZeroU32;
+use std::path::Path;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tracing::log::{error, info, warn};
+
+/// `${mirror:name}` in a button's url
+static MIRROR_PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\{mirror:([^}]+)\}").unwrap());
+
+/// Largest archive copied into the mirror, bigger ones are left out
+static MIRROR_LIMIT: Lazy<u64> = Lazy::new(|| {
+    env::var("MIRROR_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse::<u64>().ok())
+        .unwrap_or(256 * 1024 * 1024)
+});
+
+/// An artifact of a build, along with the request downloading its archive from the forge
+pub struct ArtifactSource {
+    pub name: String,
+    pub download: RequestBuilder,
+}
+
+/// Copies the selected artifacts of a successful build into the mirror, then removes the
+/// branch's copies that fall outside the retention policy. Copying can take a while, so it's
+/// done once the build's final message is out.
+pub async fn mirror_artifacts(
+    app: &App,
+    settings: &MirrorSettings,
+    build: &Build,
+    artifacts: Vec<ArtifactSource>,
+) {
+    for artifact in artifacts {
+        if !settings.includes(&artifact.name) {
+            continue;
+        }
+
+        // Retried events shouldn't download what the previous attempt already mirrored
+        let mirrored = app
+            .history
+            .mirrored_artifact(&build.repository, build.run_id, &artifact.name)
+            .await
+            .inspect_err(|e| error!("Failed to find mirrored artifact: {e}"))
+            .ok()
+            .flatten();
+
+        if mirrored.is_some() {
+            continue;
+        }
+
+        // Archives can be large, so they're spooled to disk rather than held in memory
+        let spooled = env::temp_dir().join(format!(
+            "mirror-{}.zip",
+            hex::encode(Hash::hash(
+                MirroredArtifact::key_of(&build.repository, build.run_id, &artifact.name)
+                    .as_bytes()
+            ))
+        ));
+        let downloaded = download(artifact.download, &spooled).await;
+
+        let (size, sha256) = match downloaded {
+            Ok(Some(downloaded)) => downloaded,
+            Ok(None) => {
+                warn!(
+                    "Artifact {} is larger than the mirror's limit, it isn't mirrored",
+                    artifact.name
+                );
+                remove_spooled(&spooled).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Failed to download artifact {}: {e}", artifact.name);
+                remove_spooled(&spooled).await;
+                continue;
+            }
+        };
+
+        let mirrored = MirroredArtifact {
+            repository: build.repository.clone(),
+            branch: build.branch.clone(),
+            run_id: build.run_id,
+            name: artifact.name.clone(),
+            size,
+            sha256,
+            mirrored_at: Utc::now(),
+        };
+        // Same format as `sha256sum`, so a download can be checked with `sha256sum -c`
+        let checksum = format!("{}  {}.zip\n", mirrored.sha256, mirrored.name);
+
+        let stored = async {
+            app.mirror.put_file(&mirrored.key(), &spooled).await?;
+            app.mirror
+                .put(&format!("{}.sha256", mirrored.key()), checksum.into_bytes())
+                .await?;
+            app.history.record_mirrored(mirrored).await
+        };
+
+        if let Err(e) = stored.await {
+            error!("Failed to mirror artifact {}: {e}", artifact.name);
+        }
+
+        remove_spooled(&spooled).await;
+    }
+
+    prune(
+        &app.history,
+        &app.mirror,
+        settings,
+        &build.repository,
+        &build.branch,
+    )
+    .await;
+}
+
+/// Downloads the archive into the file as it comes in, returning its size and SHA-256 hash, or
+/// nothing when it's larger than [`MIRROR_LIMIT`]
+async fn download(request: RequestBuilder, path: &Path) -> Result<Option<(u64, String)>, Error> {
+    let mut response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context(ReqwestSnafu)?;
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > *MIRROR_LIMIT)
+    {
+        return Ok(None);
+    }
+
+    let mut file = File::create(path).await.context(MirrorIoSnafu)?;
+    let mut hash = Hash::new();
+    let mut size = 0;
+
+    // The length isn't always known up front, so the limit is checked as the archive comes in
+    while let Some(chunk) = response.chunk().await.context(ReqwestSnafu)? {
+        size += chunk.len() as u64;
+
+        if size > *MIRROR_LIMIT {
+            return Ok(None);
+        }
+
+        hash.update(&chunk);
+        file.write_all(&chunk).await.context(MirrorIoSnafu)?;
+    }
+
+    file.flush().await.context(MirrorIoSnafu)?;
+
+    Ok(Some((size, hex::encode(hash.finalize()))))
+}
+
+async fn remove_spooled(path: &Path) {
+    if let Err(e) = fs::remove_file(path).await
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("Failed to remove spooled artifact {}: {e}", path.display());
+    }
+}
+
+/// Links to the mirror for the artifacts named by placeholders in the repository's button urls,
+/// pointing at the run for artifacts that aren't mirrored. The artifacts are only mirrored once
+/// the message is sent, so the links are made up front.
+pub fn mirror_variables(
+    settings: Option<&MirrorSettings>,
+    config: &RepoSettings,
+    run_url: &str,
+    build: &Build,
+) -> Vec<(String, String)> {
+    config
+        .buttons
+        .values()
+        .filter_map(|button| button.url.as_deref())
+        .flat_map(|url| MIRROR_PLACEHOLDER.captures_iter(url))
+        .map(|captures| {
+            let name = &captures[1];
+            let url = settings
+                .filter(|settings| settings.includes(name))
+                .and_then(|_| {
+                    mirror_url(&MirroredArtifact::key_of(
+                        &build.repository,
+                        build.run_id,
+                        name,
+                    ))
+                })
+                .unwrap_or_else(|| run_url.to_string());
+
+            (captures[0].to_string(), url)
+        })
+        .collect()
+}
+
+/// Signed public link to an artifact in the mirror, when both `PUBLIC_URL` and `DOWNLOAD_SECRET`
+/// are configured
+pub fn mirror_url(key: &str) -> Option<String> {
+    let public_url = env::var("PUBLIC_URL").ok()?;
+    let token = sign(key)?;
+
+    Some(format!(
+        "{}/mirror/{}?token={token}",
+        public_url.trim_end_matches('/'),
+        uri_encode(key)
+    ))
+}
+
+/// Removes the branch's mirrored artifacts that fall outside the retention policy
+async fn prune(
+    history: &BuildHistory,
+    mirror: &MirrorStorage,
+    settings: &MirrorSettings,
+    repository: &str,
+    branch: &str,
+) {
+    if settings.keep.is_none() && settings.max_age.is_none() {
+        return;
+    }
+
+    let cutoff = settings
+        .max_age
+        .map(|days| Utc::now() - Duration::days(days));
+
+    let prunable = match history
+        .prunable_mirrors(
+            repository,
+            branch,
+            settings.keep.map(NonZeroU32::get),
+            cutoff,
+        )
+        .await
+    {
+        Ok(prunable) => prunable,
+        Err(e) => {
+            error!("Failed to find mirrored artifacts to prune: {e}");
+            return;
+        }
+    };
+
+    for artifact in prunable {
+        let removed = async {
+            mirror.delete(&artifact.key()).await?;
+            mirror.delete(&format!("{}.sha256", artifact.key())).await?;
+            history.remove_mirrored(&artifact).await
+        };
+
+        match removed.await {
+            Ok(_) => info!(
+                "Pruned mirrored artifact {} of {} #{}",
+                artifact.name, artifact.repository, artifact.run_id
+            ),
+            Err(e) => error!("Failed to prune mirrored artifact {}: {e}", artifact.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPOSITORY: &str = "owner/repo";
+
+    fn artifact(branch: &str, run_id: u64, days_ago: i64) -> MirroredArtifact {
+        MirroredArtifact {
+            repository: REPOSITORY.into(),
+            branch: branch.into(),
+            run_id,
+            name: "my-mod".into(),
+            size: 0,
+            sha256: String::new(),
+            mirrored_at: Utc::now() - Duration::days(days_ago),
+        }
+    }
+
+    /// History and storage holding the artifacts, each test gets a directory of its own
+    async fn mirrored(test: &str, artifacts: &[MirroredArtifact]) -> (BuildHistory, MirrorStorage) {
+        let history = BuildHistory::open_at(":memory:").unwrap();
+        let root = env::temp_dir().join(format!("mirror-{test}-{}", std::process::id()));
+        let mirror = MirrorStorage::Local(root);
+
+        for artifact in artifacts {
+            mirror.put(&artifact.key(), Vec::new()).await.unwrap();
+            history.record_mirrored(artifact.clone()).await.unwrap();
+        }
+
+        (history, mirror)
+    }
+
+    /// Runs whose artifact is still both recorded and stored
+    async fn kept(
+        history: &BuildHistory,
+        mirror: &MirrorStorage,
+        artifacts: &[MirroredArtifact],
+    ) -> Vec<u64> {
+        let mut kept = Vec::new();
+
+        for artifact in artifacts {
+            let recorded = history
+                .mirrored_artifact(REPOSITORY, artifact.run_id, &artifact.name)
+                .await
+                .unwrap()
+                .is_some();
+            let stored = mirror.get(&artifact.key()).await.unwrap().is_some();
+
+            assert_eq!(
+                recorded, stored,
+                "run {} is only half pruned",
+                artifact.run_id
+            );
+
+            if recorded {
+                kept.push(artifact.run_id);
+            }
+        }
+
+        kept
+    }
+
+    async fn clean_up(mirror: MirrorStorage) {
+        if let MirrorStorage::Local(root) = mirror {
+            fs::remove_dir_all(root).await.unwrap();
+        }
+    }
+
+    fn settings(keep: Option<u32>, max_age: Option<i64>) -> MirrorSettings {
+        MirrorSettings {
+            artifacts: Vec::new(),
+            keep: keep.and_then(NonZeroU32::new),
+            max_age,
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_the_newest_builds_of_the_branch() {
+        let artifacts = [
+            artifact("main", 1, 3),
+            artifact("main", 2, 2),
+            artifact("main", 3, 1),
+            artifact("dev", 4, 5),
+        ];
+        let (history, mirror) = mirrored("keep", &artifacts).await;
+
+        prune(
+            &history,
+            &mirror,
+            &settings(Some(2), None),
+            REPOSITORY,
+            "main",
+        )
+        .await;
+
+        assert_eq!(kept(&history, &mirror, &artifacts).await, [2, 3, 4]);
+        clean_up(mirror).await;
+    }
+
+    #[tokio::test]
+    async fn removes_builds_older_than_the_max_age() {
+        let artifacts = [
+            artifact("main", 1, 40),
+            artifact("main", 2, 10),
+            artifact("dev", 3, 40),
+        ];
+        let (history, mirror) = mirrored("max-age", &artifacts).await;
+
+        prune(
+            &history,
+            &mirror,
+            &settings(None, Some(30)),
+            REPOSITORY,
+            "main",
+        )
+        .await;
+
+        assert_eq!(kept(&history, &mirror, &artifacts).await, [2, 3]);
+        clean_up(mirror).await;
+    }
+
+    #[tokio::test]
+    async fn keeps_everything_without_a_retention_policy() {
+        let artifacts = [artifact("main", 1, 400), artifact("main", 2, 300)];
+        let (history, mirror) = mirrored("no-policy", &artifacts).await;
+
+        prune(&history, &mirror, &settings(None, None), REPOSITORY, "main").await;
+
+        assert_eq!(kept(&history, &mirror, &artifacts).await, [1, 2]);
+        clean_up(mirror).await;
+    }
+}
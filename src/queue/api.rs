@@ -0,0 +1,49 @@
+use crate::app::App;
+use crate::error::Error;
+use crate::queue::worker::DeadLetter;
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use std::env;
+use subtle::ConstantTimeEq;
+
+pub async fn list_dead_letters(
+    State(app): State<App>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<DeadLetter>>, Error> {
+    if !authorized(&headers) {
+        return Err(Error::Unauthorized);
+    }
+
+    Ok(Json(app.queue.dead_letters()))
+}
+
+pub async fn clear_dead_letters(
+    State(app): State<App>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    if !authorized(&headers) {
+        return Err(Error::Unauthorized);
+    }
+
+    app.queue.clear_dead_letters();
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Admin endpoints are only available with `Authorization: Bearer <ADMIN_TOKEN>`, and not at all
+/// when no token is configured
+fn authorized(headers: &HeaderMap) -> bool {
+    let token = env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    match (token, provided) {
+        (Some(token), Some(provided)) => token.as_bytes().ct_eq(provided.as_bytes()).into(),
+        _ => false,
+    }
+}
@@ -0,0 +1,207 @@
+use crate::app::App;
+use crate::error::Error;
+use crate::github::verification::GithubEvent;
+use crate::gitlab::verification::GitlabEvent;
+use crate::{github, gitlab};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::log::{error, warn};
+
+/// Events waiting to be processed before new deliveries are turned away
+const CAPACITY: usize = 1_000;
+/// Failed events kept around for inspection, oldest are dropped first
+const DEAD_LETTER_LIMIT: usize = 100;
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// A verified webhook delivery from any of the supported forges
+#[derive(Clone)]
+pub enum Delivery {
+    Github(Box<GithubEvent>),
+    Gitlab(Box<GitlabEvent>),
+}
+
+struct QueuedEvent {
+    event: Delivery,
+    attempt: u32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DeadLetter {
+    pub delivery_id: Option<String>,
+    pub event: String,
+    pub repository: Option<String>,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct EventQueue {
+    sender: mpsc::Sender<QueuedEvent>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<QueuedEvent>>>,
+    dead_letters: Arc<Mutex<VecDeque<DeadLetter>>>,
+    max_attempts: u32,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel(CAPACITY);
+
+        Self {
+            sender,
+            receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+            dead_letters: Arc::new(Mutex::new(VecDeque::new())),
+            max_attempts: env::var("QUEUE_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|attempts| attempts.parse::<u32>().ok())
+                .unwrap_or(5)
+                .max(1),
+        }
+    }
+
+    /// Queues an event for processing, returning false when the queue is full
+    pub fn enqueue(&self, event: Delivery) -> bool {
+        self.sender
+            .try_send(QueuedEvent { event, attempt: 1 })
+            .is_ok()
+    }
+
+    /// Spawns the workers that process queued events
+    pub fn start(&self, app: App) {
+        let workers = env::var("QUEUE_WORKERS")
+            .ok()
+            .and_then(|workers| workers.parse::<usize>().ok())
+            .unwrap_or(4)
+            .max(1);
+
+        for _ in 0..workers {
+            let app = app.clone();
+            let queue = self.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    let Some(queued) = queue.receiver.lock().await.recv().await else {
+                        break;
+                    };
+
+                    queue.process(&app, queued).await;
+                }
+            });
+        }
+    }
+
+    async fn process(&self, app: &App, queued: QueuedEvent) {
+        let Err(e) = queued.event.clone().process(app.clone()).await else {
+            return;
+        };
+
+        let delivery = queued.event.id().unwrap_or("unknown");
+
+        if queued.attempt < self.max_attempts && e.is_transient() {
+            let delay = backoff(queued.attempt);
+            warn!(
+                "Delivery {delivery} failed on attempt {}, retrying in {}s: {e}",
+                queued.attempt,
+                delay.as_secs()
+            );
+
+            // Wait outside the worker so it can get on with other events
+            let sender = self.sender.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+
+                let retry = QueuedEvent {
+                    event: queued.event,
+                    attempt: queued.attempt + 1,
+                };
+
+                if sender.send(retry).await.is_err() {
+                    error!("Queue closed before delivery could be retried");
+                }
+            });
+
+            return;
+        }
+
+        error!(
+            "Delivery {delivery} failed after {} attempt(s), moving it to the dead letters: {e}",
+            queued.attempt
+        );
+
+        let dead_letter = DeadLetter {
+            delivery_id: queued.event.id().map(String::from),
+            event: queued.event.kind(),
+            repository: queued.event.repository(),
+            attempts: queued.attempt,
+            error: e.to_string(),
+            failed_at: Utc::now(),
+        };
+
+        let mut dead_letters = self.dead_letters.lock().unwrap();
+        if dead_letters.len() >= DEAD_LETTER_LIMIT {
+            dead_letters.pop_front();
+        }
+        dead_letters.push_back(dead_letter);
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear_dead_letters(&self) {
+        self.dead_letters.lock().unwrap().clear();
+    }
+}
+
+impl Delivery {
+    async fn process(self, app: App) -> Result<(), Error> {
+        match self {
+            Self::Github(event) => github::web::process_event(app, *event).await,
+            Self::Gitlab(event) => gitlab::web::process_event(app, *event).await,
+        }
+    }
+
+    /// Identifies the delivery in the forge's webhook logs
+    fn id(&self) -> Option<&str> {
+        match self {
+            Self::Github(event) => event.delivery_id.as_deref(),
+            Self::Gitlab(event) => event.delivery_id.as_deref(),
+        }
+    }
+
+    fn kind(&self) -> String {
+        match self {
+            Self::Github(event) => serde_json::to_value(&event.event.kind)
+                .ok()
+                .and_then(|kind| kind.as_str().map(String::from))
+                .unwrap_or_else(|| "unknown".into()),
+            Self::Gitlab(event) => event.kind.clone(),
+        }
+    }
+
+    fn repository(&self) -> Option<String> {
+        match self {
+            Self::Github(event) => event
+                .event
+                .repository
+                .as_ref()
+                .and_then(|repo| repo.full_name.clone()),
+            Self::Gitlab(event) => event
+                .payload
+                .project()
+                .map(|project| project.path_with_namespace.clone()),
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_BACKOFF)
+}
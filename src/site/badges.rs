@@ -0,0 +1,129 @@
+use crate::app::App;
+use crate::config::app_config::Config;
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::{Build, BuildStatus};
+use crate::site::visibility::ensure_public;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use maud::html;
+use serde::Serialize;
+use snafu::OptionExt;
+
+const CACHE_SECONDS: u32 = 60;
+const LABEL: &str = "build";
+
+/// Rough per-character width of 11px Verdana, which is what shields-style badges use
+const CHARACTER_WIDTH: usize = 7;
+const PADDING: usize = 10;
+
+/// Response for shields.io endpoint badges, see <https://shields.io/badges/endpoint-badge>
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShieldsEndpoint {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+    cache_seconds: u32,
+}
+
+/// Serves `/badge/{repository}/{branch}.svg` and `/badge/{repository}/{branch}.json`, where both
+/// the repository and the branch may contain slashes
+pub async fn badge(State(app): State<App>, Path(path): Path<String>) -> Result<Response, Error> {
+    let (config, badge) = Config::find_prefix(&path).context(RepositoryNotFoundSnafu)?;
+    ensure_public(&app, &config).await?;
+    let (branch, svg) = if let Some(branch) = badge.strip_suffix(".svg") {
+        (branch, true)
+    } else if let Some(branch) = badge.strip_suffix(".json") {
+        (branch, false)
+    } else {
+        return Err(Error::RepositoryNotFound);
+    };
+
+    let build = app
+        .history
+        .builds(config.full_name(), Some(branch), 1)
+        .await?
+        .into_iter()
+        .next();
+
+    let (message, color, hex) = describe(build.as_ref());
+    let cache_control = format!("max-age={CACHE_SECONDS}, s-maxage={CACHE_SECONDS}");
+
+    let response = if svg {
+        (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    "image/svg+xml; charset=utf-8".to_string(),
+                ),
+                (header::CACHE_CONTROL, cache_control),
+            ],
+            render(&message, hex),
+        )
+            .into_response()
+    } else {
+        (
+            [(header::CACHE_CONTROL, cache_control)],
+            Json(ShieldsEndpoint {
+                schema_version: 1,
+                label: LABEL,
+                message,
+                color,
+                cache_seconds: CACHE_SECONDS,
+            }),
+        )
+            .into_response()
+    };
+
+    Ok(response)
+}
+
+/// Badge message along with its shields.io color name and hex value
+fn describe(build: Option<&Build>) -> (String, &'static str, &'static str) {
+    let Some(build) = build else {
+        return ("no builds".into(), "lightgrey", "#9f9f9f");
+    };
+
+    let (status, color, hex) = match (build.status, build.conclusion.as_deref()) {
+        (BuildStatus::InProgress, _) => ("running", "yellow", "#dfb317"),
+        (BuildStatus::Completed, Some("success")) => ("passing", "brightgreen", "#4c1"),
+        (BuildStatus::Completed, Some("failure")) => ("failing", "red", "#e05d44"),
+        (BuildStatus::Completed, Some(conclusion)) => (conclusion, "lightgrey", "#9f9f9f"),
+        (BuildStatus::Completed, None) => ("unknown", "lightgrey", "#9f9f9f"),
+    };
+
+    (format!("{} | {}", build.version, status), color, hex)
+}
+
+fn render(message: &str, color: &str) -> String {
+    let label_width = LABEL.chars().count() * CHARACTER_WIDTH + PADDING;
+    let message_width = message.chars().count() * CHARACTER_WIDTH + PADDING;
+    let width = label_width + message_width;
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" width=(width) height="20" role="img" aria-label={ (LABEL) ": " (message) } {
+            title { (LABEL) ": " (message) }
+            linearGradient id="s" x2="0" y2="100%" {
+                stop offset="0" stop-color="#bbb" stop-opacity=".1" {}
+                stop offset="1" stop-opacity=".1" {}
+            }
+            clipPath id="r" {
+                rect width=(width) height="20" rx="3" fill="#fff" {}
+            }
+            g clip-path="url(#r)" {
+                rect width=(label_width) height="20" fill="#555" {}
+                rect x=(label_width) width=(message_width) height="20" fill=(color) {}
+                rect width=(width) height="20" fill="url(#s)" {}
+            }
+            g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11" {
+                text x=(label_width / 2) y="14" { (LABEL) }
+                text x=(label_width + message_width / 2) y="14" { (message) }
+            }
+        }
+    }
+    .into_string()
+}
@@ -0,0 +1,170 @@
+use crate::app::App;
+use crate::config::app_config::Config;
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::{Build, BuildStatus};
+use crate::i18n::Locale;
+use crate::site::visibility::ensure_public;
+use crate::util::time::format_duration;
+use axum::extract::{Path, State};
+use maud::{DOCTYPE, Markup, html};
+use snafu::OptionExt;
+use std::env;
+
+const RECENT_BUILDS: u32 = 25;
+
+const STYLE: &str = r"
+    body { font-family: system-ui, sans-serif; background: #1e1f22; color: #dbdee1; margin: 0 auto; max-width: 72rem; padding: 1rem; }
+    a { color: #00a8fc; text-decoration: none; }
+    a:hover { text-decoration: underline; }
+    table { width: 100%; border-collapse: collapse; margin-bottom: 2rem; }
+    th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #3f4147; vertical-align: top; }
+    .status { font-weight: bold; }
+    .success { color: #57f287; }
+    .failure { color: #ed4245; }
+    .running { color: #5865f2; }
+    .other { color: #949ba4; }
+    .links a { margin-right: 0.6rem; }
+    .muted { color: #949ba4; }
+";
+
+pub async fn index(State(app): State<App>) -> Result<Markup, Error> {
+    let config = Config::get();
+    let mut repositories = config
+        .values()
+        .map(|config| config.full_name().to_string())
+        .collect::<Vec<String>>();
+    repositories.sort();
+    repositories.dedup();
+
+    let mut sections = Vec::new();
+
+    for repository in repositories {
+        if app.history.is_private(&repository).await?
+            && !Config::find(&repository).is_some_and(|config| config.publish_private)
+        {
+            continue;
+        }
+
+        let builds = app.history.latest_builds(&repository).await?;
+
+        sections.push(html! {
+            h2 { a href={ "/repos/" (repository) } { (repository) } }
+            (branch_table(&builds))
+        });
+    }
+
+    Ok(page(
+        "Builds",
+        html! {
+            h1 { "Builds" }
+            @for section in sections { (section) }
+        },
+    ))
+}
+
+pub async fn repository(
+    State(app): State<App>,
+    Path(full_name): Path<String>,
+) -> Result<Markup, Error> {
+    let config = Config::find(&full_name).context(RepositoryNotFoundSnafu)?;
+    ensure_public(&app, &config).await?;
+
+    let latest = app.history.latest_builds(&full_name).await?;
+    let recent = app.history.builds(&full_name, None, RECENT_BUILDS).await?;
+
+    Ok(page(
+        &full_name,
+        html! {
+            p { a href="/" { "← All repositories" } }
+            h1 { a href=(config.repository_url) { (full_name) } }
+            p { a href={ "/feeds/" (full_name) ".atom" } { "Atom feed" } }
+            h2 { "Branches" }
+            (branch_table(&latest))
+            h2 { "Recent builds" }
+            table {
+                tr { th { "Build" } th { "Branch" } th { "Status" } th { "Version" } th { "Started" } th { "Commits" } }
+                @for build in &recent {
+                    tr {
+                        td { a href=(build.url) { "#" (build.run_number) } }
+                        td { (build.branch) }
+                        td { (status(build)) }
+                        td { (build.version) }
+                        td { (build.started_at.format("%Y-%m-%d %H:%M UTC")) }
+                        td {
+                            @for commit in &build.commits {
+                                div {
+                                    a href=(commit.url) { (commit.id.chars().take(7).collect::<String>()) }
+                                    " " (commit.message.lines().next().unwrap_or_default())
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+    ))
+}
+
+fn page(title: &str, content: Markup) -> Markup {
+    let refresh = env::var("DASHBOARD_REFRESH")
+        .ok()
+        .and_then(|refresh| refresh.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                meta http-equiv="refresh" content=(refresh);
+                title { (title) " - CI Preview" }
+                style { (STYLE) }
+            }
+            body { (content) }
+        }
+    }
+}
+
+fn branch_table(builds: &[Build]) -> Markup {
+    html! {
+        @if builds.is_empty() {
+            p.muted { "No builds yet" }
+        } @else {
+            table {
+                tr { th { "Branch" } th { "Status" } th { "Build" } th { "Version" } th { "Duration" } th { "Downloads" } }
+                @for build in builds {
+                    tr {
+                        td { (build.branch) }
+                        td { (status(build)) }
+                        td { a href=(build.url) { "#" (build.run_number) } }
+                        td { (build.version) }
+                        td {
+                            @if let Some(duration) = build.duration {
+                                (format_duration(Locale::default(), duration))
+                            }
+                        }
+                        td.links {
+                            @for link in &build.links {
+                                a href=(link.url) { (link.label) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn status(build: &Build) -> Markup {
+    let (label, class) = match (build.status, build.conclusion.as_deref()) {
+        (BuildStatus::InProgress, _) => ("Running", "running"),
+        (BuildStatus::Completed, Some("success")) => ("Success", "success"),
+        (BuildStatus::Completed, Some("failure")) => ("Failed", "failure"),
+        (BuildStatus::Completed, Some(conclusion)) => (conclusion, "other"),
+        (BuildStatus::Completed, None) => ("Unknown", "other"),
+    };
+
+    html! { span class={ "status " (class) } { (label) } }
+}
@@ -0,0 +1,218 @@
+use crate::app::App;
+use crate::config::app_config::Config;
+use crate::error::Error;
+use crate::error::*;
+use crate::history::store::Build;
+use crate::site::visibility::ensure_public;
+use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use maud::html;
+use reqwest::Url;
+use serde::Deserialize;
+use snafu::OptionExt;
+use std::env;
+
+const FEED_ENTRIES: u32 = 50;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub branch: Option<String>,
+}
+
+enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+/// Serves `/feeds/{repository}.atom` and `/feeds/{repository}.rss`
+pub async fn feed(
+    State(app): State<App>,
+    Path(feed): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, Error> {
+    let (full_name, format) = if let Some(full_name) = feed.strip_suffix(".atom") {
+        (full_name, FeedFormat::Atom)
+    } else if let Some(full_name) = feed.strip_suffix(".rss") {
+        (full_name, FeedFormat::Rss)
+    } else {
+        return Err(Error::RepositoryNotFound);
+    };
+
+    let config = Config::find(full_name).context(RepositoryNotFoundSnafu)?;
+    ensure_public(&app, &config).await?;
+
+    let builds = app
+        .history
+        .successful_builds(full_name, query.branch.as_deref(), FEED_ENTRIES)
+        .await?;
+
+    let title = match &query.branch {
+        Some(branch) => format!("{full_name}/{branch} builds"),
+        None => format!("{full_name} builds"),
+    };
+
+    let self_url = env::var("PUBLIC_URL").ok().and_then(|public_url| {
+        let extension = match format {
+            FeedFormat::Atom => "atom",
+            FeedFormat::Rss => "rss",
+        };
+        let mut url = Url::parse(&format!(
+            "{}/feeds/{full_name}.{extension}",
+            public_url.trim_end_matches('/')
+        ))
+        .ok()?;
+
+        // Branches can contain anything from slashes to ampersands
+        if let Some(branch) = &query.branch {
+            url.query_pairs_mut().append_pair("branch", branch);
+        }
+
+        Some(url.to_string())
+    });
+
+    let (content_type, body) = match format {
+        FeedFormat::Atom => (
+            "application/atom+xml; charset=utf-8",
+            atom(&title, &config, self_url.as_deref(), &builds),
+        ),
+        FeedFormat::Rss => (
+            "application/rss+xml; charset=utf-8",
+            rss(&title, &config, self_url.as_deref(), &builds),
+        ),
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+fn atom(title: &str, config: &Config, self_url: Option<&str>, builds: &[Build]) -> String {
+    let updated = builds
+        .first()
+        .map(completed_at)
+        .unwrap_or_default()
+        .to_rfc3339();
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml += r#"<feed xmlns="http://www.w3.org/2005/Atom">"#;
+    xml += &format!("<id>{}</id>", escape(&config.repository_url));
+    xml += &format!("<title>{}</title>", escape(title));
+    xml += &format!("<updated>{updated}</updated>");
+    xml += "<author><name>CI Preview</name></author>";
+    xml += &format!(
+        r#"<link rel="alternate" href="{}"/>"#,
+        escape(&config.repository_url)
+    );
+
+    if let Some(self_url) = self_url {
+        xml += &format!(r#"<link rel="self" href="{}"/>"#, escape(self_url));
+    }
+
+    for build in builds {
+        xml += "<entry>";
+        xml += &format!("<id>{}</id>", escape(&entry_id(build)));
+        xml += &format!("<title>{}</title>", escape(&entry_title(build)));
+        xml += &format!("<updated>{}</updated>", completed_at(build).to_rfc3339());
+        xml += &format!(r#"<link rel="alternate" href="{}"/>"#, escape(&build.url));
+
+        for link in &build.links {
+            xml += &format!(
+                r#"<link rel="enclosure" href="{}" title="{}"/>"#,
+                escape(&link.url),
+                escape(&link.label)
+            );
+        }
+
+        xml += &format!(
+            r#"<content type="html">{}</content>"#,
+            escape(&content(build))
+        );
+        xml += "</entry>";
+    }
+
+    xml += "</feed>";
+    xml
+}
+
+fn rss(title: &str, config: &Config, self_url: Option<&str>, builds: &[Build]) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml += r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#;
+    xml += &format!("<title>{}</title>", escape(title));
+    xml += &format!("<link>{}</link>", escape(&config.repository_url));
+    xml += &format!("<description>{}</description>", escape(title));
+
+    if let Some(self_url) = self_url {
+        xml += &format!(
+            r#"<atom:link rel="self" type="application/rss+xml" href="{}"/>"#,
+            escape(self_url)
+        );
+    }
+
+    for build in builds {
+        xml += "<item>";
+        xml += &format!(
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            escape(&entry_id(build))
+        );
+        xml += &format!("<title>{}</title>", escape(&entry_title(build)));
+        xml += &format!("<link>{}</link>", escape(&build.url));
+        xml += &format!("<pubDate>{}</pubDate>", completed_at(build).to_rfc2822());
+
+        // RSS only allows a single enclosure per item, the rest are linked in the description
+        if let Some(link) = build.links.first() {
+            xml += &format!(
+                r#"<enclosure url="{}" length="0" type="application/octet-stream"/>"#,
+                escape(&link.url)
+            );
+        }
+
+        xml += &format!("<description>{}</description>", escape(&content(build)));
+        xml += "</item>";
+    }
+
+    xml += "</channel></rss>";
+    xml
+}
+
+fn entry_id(build: &Build) -> String {
+    format!("{}#attempt-{}", build.url, build.run_attempt)
+}
+
+fn entry_title(build: &Build) -> String {
+    format!("{} #{} - {}", build.branch, build.run_number, build.version)
+}
+
+fn completed_at(build: &Build) -> DateTime<Utc> {
+    build.completed_at.unwrap_or(build.started_at)
+}
+
+fn content(build: &Build) -> String {
+    html! {
+        p { "Version: " strong { (build.version) } }
+        @if !build.links.is_empty() {
+            p {
+                @for link in &build.links {
+                    a href=(link.url) { (link.label) } " "
+                }
+            }
+        }
+        ul {
+            @for commit in &build.commits {
+                li {
+                    a href=(commit.url) { (commit.id.chars().take(7).collect::<String>()) }
+                    " " (commit.message.lines().next().unwrap_or_default())
+                    " - " (commit.author.username.as_deref().unwrap_or(&commit.author.name))
+                }
+            }
+        }
+    }
+    .into_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
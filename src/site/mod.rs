@@ -0,0 +1,4 @@
+pub mod badges;
+pub mod dashboard;
+pub mod feeds;
+pub mod visibility;
@@ -0,0 +1,13 @@
+use crate::app::App;
+use crate::config::app_config::Config;
+use crate::error::Error;
+
+/// Keeps a private repository's builds off public pages unless its config publishes them, it's
+/// answered like a repository that isn't configured so its name doesn't leak either
+pub async fn ensure_public(app: &App, config: &Config) -> Result<(), Error> {
+    if !config.publish_private && app.history.is_private(config.full_name()).await? {
+        return Err(Error::RepositoryNotFound);
+    }
+
+    Ok(())
+}
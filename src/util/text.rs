@@ -0,0 +1,62 @@
+use crate::i18n::Locale;
+
+/// Joins as many lines as fit within the limit, ending with a note of how many were left out
+pub fn truncated_list(lines: &[String], limit: usize, locale: Locale) -> String {
+    let more = |count: usize| locale.plural("build.more", count as i64, &[]);
+    let mut output = String::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        // Every line but the last has to leave room for the note that would follow it
+        let left_out = lines.len() - index - 1;
+        let after = match left_out {
+            0 => 0,
+            left_out => 1 + more(left_out).len(),
+        };
+
+        if output.len() + line.len() + after > limit {
+            output += &more(lines.len() - index);
+            return output;
+        }
+
+        output += line;
+        output += "\n";
+    }
+
+    output.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        (1..=count).map(|line| format!("line {line}")).collect()
+    }
+
+    #[test]
+    fn joins_lines_that_fit() {
+        assert_eq!(
+            truncated_list(&lines(3), 100, Locale::default()),
+            "line 1\nline 2\nline 3"
+        );
+    }
+
+    #[test]
+    fn notes_how_many_lines_were_left_out() {
+        let list = truncated_list(&lines(10), 40, Locale::default());
+
+        assert_eq!(list, "line 1\nline 2\nline 3\n…and 7 more");
+        assert!(list.len() <= 40);
+    }
+
+    #[test]
+    fn uses_the_room_of_the_note_for_the_last_line() {
+        let lines = vec!["x".repeat(20); 3];
+
+        assert_eq!(
+            truncated_list(&lines, 62, Locale::default()),
+            lines.join("\n")
+        );
+        assert!(truncated_list(&lines, 61, Locale::default()).ends_with("…and 1 more"));
+    }
+}
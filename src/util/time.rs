@@ -1,28 +1,30 @@
-pub fn format_duration(total_seconds: i64) -> String {
+use crate::i18n::Locale;
+
+pub fn format_duration(locale: Locale, total_seconds: i64) -> String {
     let hours = total_seconds / 3600;
     let minutes = (total_seconds % 3600) / 60;
     let seconds = total_seconds % 60;
 
     let mut output = String::new();
 
-    if hours > 0 {
-        output += &format!("{} hour{}", hours, if hours == 1 { "" } else { "s" });
-    }
-
-    if minutes > 0 {
-        if !output.is_empty() {
-            output += ", ";
+    for (key, count) in [
+        ("duration.hours", hours),
+        ("duration.minutes", minutes),
+        ("duration.seconds", seconds),
+    ] {
+        if count <= 0 {
+            continue;
         }
 
-        output += &format!("{} minute{}", minutes, if minutes == 1 { "" } else { "s" });
-    }
-
-    if seconds > 0 {
+        // Only the seconds are joined with "and", so "1 hour, 2 minutes" reads as it always has
         if !output.is_empty() {
-            output += " and ";
+            output += &locale.text(match key {
+                "duration.seconds" => "duration.last_separator",
+                _ => "duration.separator",
+            });
         }
 
-        output += &format!("{} second{}", seconds, if seconds == 1 { "" } else { "s" });
+        output += &locale.plural(key, count, &[]);
     }
 
     output